@@ -1,14 +1,81 @@
-use bech32::{self, FromBase32};
-use ckb_types::bytes::Bytes;
+use crate::{DEFAULT_CODE_HASH, MULTISIG_CODE_HASH};
+use bech32::{self, FromBase32, ToBase32};
+use ckb_types::{bytes::Bytes, core::ScriptHashType, packed::Script, prelude::*, H256};
 use failure::{Error, Fail};
+use serde_derive::Deserialize;
 use std::fmt;
+use std::str::FromStr;
 
 const TESTNET_PREFIX: &str = "ckt";
 const MAINNET_PREFIX: &str = "ckb";
 
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkType {
+    Mainnet,
+    Testnet,
+}
+
+impl NetworkType {
+    pub fn from_prefix(hrp: &str) -> Option<NetworkType> {
+        match hrp {
+            MAINNET_PREFIX => Some(NetworkType::Mainnet),
+            TESTNET_PREFIX => Some(NetworkType::Testnet),
+            _ => None,
+        }
+    }
+
+    pub fn to_prefix(self) -> &'static str {
+        match self {
+            NetworkType::Mainnet => MAINNET_PREFIX,
+            NetworkType::Testnet => TESTNET_PREFIX,
+        }
+    }
+}
+
+impl Default for NetworkType {
+    fn default() -> Self {
+        NetworkType::Mainnet
+    }
+}
+
+impl fmt::Display for NetworkType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkType::Mainnet => write!(f, "mainnet"),
+            NetworkType::Testnet => write!(f, "testnet"),
+        }
+    }
+}
+
+/// Code hash indices of the short payload format.
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Copy)]
+pub enum CodeHashIndex {
+    /// SECP256K1 + blake160
+    Sighash = 0x00,
+    /// SECP256K1 + multisig, the args is the multisig script hash
+    Multisig = 0x01,
+}
+
+#[derive(Hash, Eq, PartialEq, Debug, Clone)]
+pub enum AddressPayload {
+    Short {
+        index: CodeHashIndex,
+        args: Bytes,
+    },
+    /// Lock script with an explicit code hash, format type 0x02 for hash type data and 0x04 for
+    /// hash type type.
+    Full {
+        hash_type: ScriptHashType,
+        code_hash: H256,
+        args: Bytes,
+    },
+}
+
 #[derive(Hash, Eq, PartialEq, Debug, Clone)]
 pub struct Address {
-    pub args: Bytes,
+    pub network: NetworkType,
+    pub payload: AddressPayload,
 }
 
 #[derive(Hash, Eq, PartialEq, Debug, Clone, Fail)]
@@ -21,33 +88,318 @@ impl fmt::Display for AddressError {
 }
 
 impl Address {
-    pub fn new(args: Bytes) -> Address {
-        Address { args }
+    pub fn new(network: NetworkType, payload: AddressPayload) -> Address {
+        Address { network, payload }
     }
 
     pub fn from_str(input: &str) -> Result<Address, Error> {
         let (hrp, data) = bech32::decode(input)?;
         let data = Vec::<u8>::from_base32(&data)?;
-        if hrp != TESTNET_PREFIX && hrp != MAINNET_PREFIX {
-            return Err(AddressError(format!("Invalid address hrp: {}", hrp)).into());
+        let network = NetworkType::from_prefix(&hrp)
+            .ok_or_else(|| AddressError(format!("Invalid address hrp: {}", hrp)))?;
+        let payload = match data.first() {
+            // short version for locks with popular code_hash
+            Some(0x01) if data.len() == 22 => {
+                let index = match data[1] {
+                    0x00 => CodeHashIndex::Sighash,
+                    0x01 => CodeHashIndex::Multisig,
+                    index => {
+                        return Err(
+                            AddressError(format!("Invalid code hash index: {}", index)).into()
+                        );
+                    }
+                };
+                AddressPayload::Short {
+                    index,
+                    args: Bytes::from(&data[2..22]),
+                }
+            }
+            Some(0x01) if data.len() == 25 => {
+                if &data[0..5] != b"\x01P2PH" {
+                    return Err(
+                        AddressError(format!("Invalid format type: {:?}", &data[0..5])).into(),
+                    );
+                }
+                AddressPayload::Short {
+                    index: CodeHashIndex::Sighash,
+                    args: Bytes::from(&data[5..25]),
+                }
+            }
+            Some(0x01) => {
+                return Err(
+                    AddressError(format!("Invalid Address data length: {}", data.len())).into(),
+                );
+            }
+            Some(0x02) | Some(0x04) => {
+                if data.len() < 33 {
+                    return Err(AddressError(format!(
+                        "Invalid Address data length: {}",
+                        data.len()
+                    ))
+                    .into());
+                }
+                AddressPayload::Full {
+                    hash_type: if data[0] == 0x02 {
+                        ScriptHashType::Data
+                    } else {
+                        ScriptHashType::Type
+                    },
+                    code_hash: H256::from_slice(&data[1..33])
+                        .map_err(|e| AddressError(format!("Invalid code hash: {:?}", e)))?,
+                    args: Bytes::from(&data[33..]),
+                }
+            }
+            Some(format_type) => {
+                return Err(AddressError(format!("Invalid address type: {}", format_type)).into());
+            }
+            None => return Err(AddressError("Empty address data".to_string()).into()),
+        };
+        Ok(Address::new(network, payload))
+    }
+
+    /// Parses an address which must belong to `network`.
+    pub fn from_str_on(input: &str, network: NetworkType) -> Result<Address, Error> {
+        let address = Address::from_str(input)?;
+        if address.network != network {
+            return Err(AddressError(format!(
+                "Expect {} address, got {} address",
+                network, address.network
+            ))
+            .into());
         }
-        if data.len() == 22 {
-            if data[0] != 0x01 {
-                // short version for locks with popular code_hash
-                return Err(AddressError(format!("Invalid address type: {}", data[0])).into());
+        Ok(address)
+    }
+
+    /// Converts a lock script, using the short payload format for the sighash and multisig locks.
+    pub fn from_script(script: &Script, network: NetworkType) -> Result<Address, Error> {
+        let hash_type = match script.hash_type().as_slice()[0] {
+            0 => ScriptHashType::Data,
+            1 => ScriptHashType::Type,
+            hash_type => {
+                return Err(AddressError(format!("Invalid hash type: {}", hash_type)).into());
+            }
+        };
+        let code_hash = H256::from_slice(script.code_hash().as_slice())
+            .map_err(|e| AddressError(format!("Invalid code hash: {:?}", e)))?;
+        let args = script.args().raw_data();
+        let index = match hash_type {
+            ScriptHashType::Type if code_hash == sighash_code_hash() => {
+                Some(CodeHashIndex::Sighash)
+            }
+            ScriptHashType::Type if code_hash == multisig_code_hash() => {
+                Some(CodeHashIndex::Multisig)
             }
-            if data[1] != 0x00 {
-                // SECP256K1 + blake160
-                return Err(AddressError(format!("Invalid code hash index: {}", data[1])).into());
+            _ => None,
+        };
+        let payload = match index {
+            Some(index) if args.len() == 20 => AddressPayload::Short { index, args },
+            _ => AddressPayload::Full {
+                hash_type,
+                code_hash,
+                args,
+            },
+        };
+        Ok(Address::new(network, payload))
+    }
+
+    /// Encodes the address for `network`, in the format it was parsed or built with. Addresses in
+    /// the legacy format are encoded in the short payload format.
+    pub fn encode(&self, network: NetworkType) -> String {
+        let mut data = vec![self.format_type()];
+        match self.payload {
+            AddressPayload::Short { index, .. } => data.push(index as u8),
+            AddressPayload::Full { ref code_hash, .. } => {
+                data.extend_from_slice(code_hash.as_bytes())
             }
-            Ok(Address::new(Bytes::from(&data[2..22])))
-        } else if data.len() == 25 {
-            if &data[0..5] != b"\x01P2PH" {
-                return Err(AddressError(format!("Invalid format type: {:?}", &data[0..5])).into());
+        }
+        data.extend_from_slice(self.args());
+        bech32::encode(network.to_prefix(), data.to_base32()).unwrap()
+    }
+
+    /// 0x01 for the short payload format, 0x02 and 0x04 for the full payload format with hash
+    /// type data and type.
+    pub fn format_type(&self) -> u8 {
+        match self.payload {
+            AddressPayload::Short { .. } => 0x01,
+            AddressPayload::Full {
+                hash_type: ScriptHashType::Data,
+                ..
+            } => 0x02,
+            AddressPayload::Full {
+                hash_type: ScriptHashType::Type,
+                ..
+            } => 0x04,
+        }
+    }
+
+    /// The code hash of the lock script, in hex with the 0x prefix.
+    pub fn code_hash(&self) -> String {
+        match self.payload {
+            AddressPayload::Short {
+                index: CodeHashIndex::Sighash,
+                ..
+            } => DEFAULT_CODE_HASH.to_string(),
+            AddressPayload::Short {
+                index: CodeHashIndex::Multisig,
+                ..
+            } => MULTISIG_CODE_HASH.to_string(),
+            AddressPayload::Full { ref code_hash, .. } => {
+                format!(
+                    "0x{}",
+                    faster_hex::hex_string(code_hash.as_bytes()).unwrap()
+                )
             }
-            Ok(Address::new(Bytes::from(&data[5..25])))
-        } else {
-            Err(AddressError(format!("Invalid Address data length: {}", data.len())).into())
         }
     }
+
+    pub fn hash_type(&self) -> ScriptHashType {
+        match self.payload {
+            AddressPayload::Short { .. } => ScriptHashType::Type,
+            AddressPayload::Full { hash_type, .. } => hash_type,
+        }
+    }
+
+    pub fn args(&self) -> &Bytes {
+        match self.payload {
+            AddressPayload::Short { ref args, .. } | AddressPayload::Full { ref args, .. } => args,
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.encode(self.network))
+    }
+}
+
+impl<'a> From<&'a Address> for Script {
+    fn from(address: &'a Address) -> Script {
+        match address.payload {
+            AddressPayload::Short { index, ref args } => short_lock(index, args),
+            AddressPayload::Full {
+                hash_type,
+                ref code_hash,
+                ref args,
+            } => Script::new_builder()
+                .code_hash(code_hash.pack())
+                .hash_type(hash_type.into())
+                .args(args.pack())
+                .build(),
+        }
+    }
+}
+
+/// The sighash or multisig lock script with `args`.
+pub fn short_lock(index: CodeHashIndex, args: &Bytes) -> Script {
+    let code_hash = match index {
+        CodeHashIndex::Sighash => sighash_code_hash(),
+        CodeHashIndex::Multisig => multisig_code_hash(),
+    };
+    Script::new_builder()
+        .code_hash(code_hash.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(args.pack())
+        .build()
+}
+
+pub fn sighash_code_hash() -> H256 {
+    H256::from_str(&DEFAULT_CODE_HASH[2..]).unwrap()
+}
+
+pub fn multisig_code_hash() -> H256 {
+    H256::from_str(&MULTISIG_CODE_HASH[2..]).unwrap()
+}
+
+/// The name of a hash type in the chain spec.
+pub fn hash_type_name(hash_type: ScriptHashType) -> &'static str {
+    match hash_type {
+        ScriptHashType::Data => "data",
+        ScriptHashType::Type => "type",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_payload() {
+        let short = Address::from_str("ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq").unwrap();
+        let full = Address::from_str(
+            "ckb1qjda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xw3736lgsngvvlj3cu7ggzfupjsvdugeg66n9dal8",
+        )
+        .unwrap();
+        assert_eq!(full.code_hash(), short.code_hash());
+        assert_eq!(full.hash_type(), ScriptHashType::Type);
+        assert_eq!(full.args(), short.args());
+
+        let data =
+            Address::from_str("ckt1qgqqzqsrqszsvpcgpy9qkrqdpc83qygjzv2p29shrqv35xcur50pl27d8w34td")
+                .unwrap();
+        assert_eq!(data.network, NetworkType::Testnet);
+        assert_eq!(data.hash_type(), ScriptHashType::Data);
+        assert_eq!(
+            data.code_hash(),
+            "0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+        );
+        assert_eq!(&data.args()[..], b"\xab\xcd");
+    }
+
+    #[test]
+    fn test_short_multisig() {
+        let address = Address::from_str("ckb1qyqlqn8vsj7r0a5rvya76tey9jd2rdnca8lq2sg8su").unwrap();
+        assert_eq!(address.code_hash(), MULTISIG_CODE_HASH);
+        assert_eq!(
+            faster_hex::hex_string(&address.args()[..]).unwrap(),
+            "f04cec84bc37f683613bed2f242c9aa1b678e9fe"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let inputs = [
+            "ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq",
+            "ckb1qyqlqn8vsj7r0a5rvya76tey9jd2rdnca8lq2sg8su",
+            "ckb1qjda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xw3736lgsngvvlj3cu7ggzfupjsvdugeg66n9dal8",
+            "ckt1qgqqzqsrqszsvpcgpy9qkrqdpc83qygjzv2p29shrqv35xcur50pl27d8w34td",
+            "ckt1qyqdmswal8qn2psmwc6u5508xh7zkq7wuvustsvyew",
+        ];
+        for input in inputs.iter() {
+            let address = Address::from_str(input).unwrap();
+            assert_eq!(&address.to_string(), input);
+            assert_eq!(
+                Address::from_str(&address.encode(NetworkType::Testnet))
+                    .unwrap()
+                    .payload,
+                address.payload
+            );
+        }
+
+        let legacy =
+            Address::from_str("ckt1q9gry5zgx5r2xequz62m0rhvy60xvsqj5azl5efd3knr83").unwrap();
+        assert_eq!(Address::from_str(&legacy.to_string()).unwrap(), legacy);
+    }
+
+    #[test]
+    fn test_script() {
+        let short = Address::from_str("ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq").unwrap();
+        let full = Address::from_str(
+            "ckb1qjda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xw3736lgsngvvlj3cu7ggzfupjsvdugeg66n9dal8",
+        )
+        .unwrap();
+        let script = Script::from(&full);
+        assert_eq!(script.as_slice(), Script::from(&short).as_slice());
+        assert_eq!(
+            Address::from_script(&script, NetworkType::Mainnet).unwrap(),
+            short
+        );
+
+        let data =
+            Address::from_str("ckt1qgqqzqsrqszsvpcgpy9qkrqdpc83qygjzv2p29shrqv35xcur50pl27d8w34td")
+                .unwrap();
+        assert_eq!(
+            Address::from_script(&Script::from(&data), NetworkType::Testnet).unwrap(),
+            data
+        );
+    }
 }
@@ -1,38 +1,159 @@
+use crate::manifest::InputError;
 use chrono::{
-    naive::NaiveDate,
-    offset::{TimeZone, Utc},
-    DateTime,
+    naive::{NaiveDate, NaiveDateTime},
+    offset::{LocalResult, TimeZone, Utc},
+    DateTime, Duration, SecondsFormat,
 };
+use chrono_tz::Tz;
 use ckb_types::core::EpochNumberWithFraction;
 use failure::Error;
+use std::str::FromStr;
 
 const EPOCH_DURATION: u64 = 4 * 60 * 60;
 const EPOCH_LENGTH: u64 = 1_800;
 const SINCE_FLAG: u64 = 0x2000_0000_0000_0000;
+const BASE_EPOCH: u64 = 89;
 
 pub fn parse_date(input: &str) -> Result<DateTime<Utc>, Error> {
     let date = NaiveDate::parse_from_str(input, "%Y-%m-%d")?.and_hms(0, 0, 0);
     Ok(DateTime::from_utc(date, Utc))
 }
 
-pub struct Outset;
+/// Parses a lock time, which is one of
+///
+/// - a date in the format YYYY-MM-DD, at 00:00 in UTC,
+/// - an RFC 3339 timestamp such as "2020-07-01T09:00:00+08:00",
+/// - a local date or time followed by an IANA timezone, such as
+///   "2020-07-01T09:00:00 Asia/Shanghai" or "2020-07-01 Europe/Zurich".
+///
+/// Local times which are skipped or repeated in the timezone are rejected.
+pub fn parse_time(input: &str) -> Result<DateTime<Utc>, Error> {
+    let invalid = || InputError(format!("invalid lock time: {}", input));
+    if let Ok(date) = parse_date(input) {
+        return Ok(date);
+    }
+    let (local, zone) = match input.rfind(' ') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => {
+            return DateTime::parse_from_rfc3339(input)
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(|_| invalid().into());
+        }
+    };
+    if DateTime::parse_from_rfc3339(local).is_ok() {
+        return Err(InputError(format!(
+            "lock time has both an offset and a timezone: {}",
+            input
+        ))
+        .into());
+    }
+    let tz: Tz = zone
+        .parse()
+        .map_err(|_| InputError(format!("unknown timezone: {}", zone)))?;
+    let naive = NaiveDateTime::parse_from_str(local, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDate::parse_from_str(local, "%Y-%m-%d").map(|date| date.and_hms(0, 0, 0)))
+        .map_err(|_| invalid())?;
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(dt) => Ok(dt.with_timezone(&Utc)),
+        LocalResult::Ambiguous(_, _) => {
+            Err(InputError(format!("ambiguous local time in {}: {}", zone, local)).into())
+        }
+        LocalResult::None => {
+            Err(InputError(format!("nonexistent local time in {}: {}", zone, local)).into())
+        }
+    }
+}
+
+/// Formats a time in UTC as RFC 3339, such as "2020-07-01T01:00:00Z".
+pub fn format_time(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Where the epoch duration of a calibrated conversion comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Calibration {
+    /// The average epoch duration of the testnet before the target epoch.
+    Measured,
+    /// The measured duration scaled by the genesis difficulty over the testnet difficulty.
+    Projected,
+}
+
+impl FromStr for Calibration {
+    type Err = InputError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "measured" => Ok(Calibration::Measured),
+            "projected" => Ok(Calibration::Projected),
+            _ => Err(InputError(format!(
+                "invalid calibration: {}, expect measured or projected",
+                input
+            ))),
+        }
+    }
+}
+
+/// How dates are converted to epochs of the launched chain. The defaults reproduce the Lina
+/// launch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outset {
+    /// The time when epoch `base_epoch` of the testnet starts.
+    pub anchor: DateTime<Utc>,
+    pub base_epoch: u64,
+    /// Expected duration of an epoch in seconds.
+    pub epoch_duration: u64,
+    /// Number of blocks in an epoch, which is the denominator of the epoch fraction.
+    pub epoch_length: u64,
+}
+
+impl Default for Outset {
+    fn default() -> Self {
+        Outset {
+            anchor: Utc.ymd(2019, 11, 16).and_hms(6, 0, 0),
+            base_epoch: BASE_EPOCH,
+            epoch_duration: EPOCH_DURATION,
+            epoch_length: EPOCH_LENGTH,
+        }
+    }
+}
 
 impl Outset {
-    pub fn since(&self, date: &DateTime<Utc>) -> u64 {
-        (date.timestamp() - Utc.ymd(2019, 11, 16).and_hms(6, 0, 0).timestamp()) as u64
+    /// Seconds from the anchor to `date`, negative if `date` is before the anchor.
+    pub fn since(&self, date: &DateTime<Utc>) -> i64 {
+        date.timestamp() - self.anchor.timestamp()
+    }
+
+    /// Estimates the date of an epoch in the chain launched at the end of epoch `target`, the
+    /// inverse of `since_epoch`.
+    pub fn epoch_date(&self, epoch: EpochNumberWithFraction, target: u64) -> DateTime<Utc> {
+        let fraction = if epoch.length() > 0 {
+            epoch.index() * self.epoch_duration / epoch.length()
+        } else {
+            0
+        };
+        let epochs = (epoch.number() + target) as i64 - self.base_epoch as i64;
+        self.anchor + Duration::seconds(epochs * self.epoch_duration as i64 + fraction as i64)
     }
 
     pub fn since_epoch(&self, date: &DateTime<Utc>, target: u64) -> u64 {
         let since = self.since(date);
-        let offset = (since / EPOCH_DURATION) + 89;
-        let overflow = target > offset;
-        let epoch = if !overflow { offset - target } else { 0 };
-        let index = if !overflow {
-            (since % EPOCH_DURATION) * 1800 / EPOCH_DURATION
+        let duration = self.epoch_duration as i64;
+        let offset = since.div_euclid(duration) + self.base_epoch as i64;
+        // locks before the launch are clamped to epoch 0
+        let (epoch, index) = if offset >= target as i64 {
+            let index = since.rem_euclid(duration) as u64 * self.epoch_length / self.epoch_duration;
+            ((offset - target as i64) as u64, index)
         } else {
-            0
+            (0, 0)
         };
-        EpochNumberWithFraction::new(epoch, index, EPOCH_LENGTH).full_value() + SINCE_FLAG
+        EpochNumberWithFraction::new(epoch, index, self.epoch_length).full_value() + SINCE_FLAG
+    }
+
+    /// How much later than `date` the lock converted by `self` unlocks, if the epochs actually
+    /// last as assumed by `actual`.
+    pub fn drift(&self, actual: &Outset, date: &DateTime<Utc>, target: u64) -> Duration {
+        let since = self.since_epoch(date, target) - SINCE_FLAG;
+        actual.epoch_date(EpochNumberWithFraction::from_full_value(since), target) - *date
     }
 }
 
@@ -46,4 +167,63 @@ mod tests {
         assert!(dt.is_ok(), "{:?}", dt);
         assert_eq!(dt.unwrap(), Utc.ymd(2020, 1, 1).and_hms(0, 0, 0));
     }
+
+    #[test]
+    fn test_parse_time() {
+        let utc = Utc.ymd(2020, 7, 1).and_hms(1, 0, 0);
+        assert_eq!(parse_time("2020-07-01T09:00:00+08:00").unwrap(), utc);
+        assert_eq!(
+            parse_time("2020-07-01T09:00:00 Asia/Shanghai").unwrap(),
+            utc
+        );
+        assert_eq!(
+            parse_time("2020-07-01 Europe/Zurich").unwrap(),
+            Utc.ymd(2020, 6, 30).and_hms(22, 0, 0)
+        );
+        assert_eq!(
+            parse_time("2020-07-01").unwrap(),
+            Utc.ymd(2020, 7, 1).and_hms(0, 0, 0)
+        );
+        // skipped and repeated by the daylight saving time
+        assert!(parse_time("2020-03-29T02:30:00 Europe/Zurich").is_err());
+        assert!(parse_time("2020-10-25T02:30:00 Europe/Zurich").is_err());
+        assert!(parse_time("2020-07-01T09:00:00+08:00 Asia/Shanghai").is_err());
+        assert!(parse_time("2020-07-01T09:00:00").is_err());
+        assert!(parse_time("2020-07-01 Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn test_since_epoch() {
+        let outset = Outset::default();
+        let dt = parse_date("2020-07-01").unwrap();
+        assert_eq!(outset.since_epoch(&dt, 89), 0x2007_0803_8400_0556);
+        // before the launch
+        let dt = parse_date("2019-11-01").unwrap();
+        assert_eq!(outset.since_epoch(&dt, 89), 0x2007_0800_0000_0000);
+        // before the anchor but after the launch
+        let dt = outset.anchor - Duration::seconds(1);
+        assert_eq!(outset.since_epoch(&dt, 0), 0x2007_0807_0700_0058);
+    }
+
+    #[test]
+    fn test_epoch_date() {
+        let dt = parse_date("2020-07-01").unwrap();
+        let outset = Outset::default();
+        let since = outset.since_epoch(&dt, 89) - SINCE_FLAG;
+        let epoch = EpochNumberWithFraction::from_full_value(since);
+        assert_eq!(outset.epoch_date(epoch, 89), dt);
+    }
+
+    #[test]
+    fn test_drift() {
+        let dt = parse_date("2020-07-01").unwrap();
+        let nominal = Outset::default();
+        let calibrated = Outset {
+            epoch_duration: 14_400 + 36,
+            ..nominal
+        };
+        assert_eq!(nominal.drift(&nominal, &dt, 89), Duration::zero());
+        assert!(nominal.drift(&calibrated, &dt, 89) > Duration::hours(1));
+        assert!(calibrated.drift(&calibrated, &dt, 89) < Duration::seconds(36));
+    }
 }
@@ -1,5 +1,11 @@
-use crate::rpc::RpcClient;
+use crate::{
+    address::CodeHashIndex,
+    date::Calibration,
+    fetcher::{ranges, Fetcher},
+    rpc::RpcClient,
+};
 use chrono::{prelude::*, Duration};
+use ckb_jsonrpc_types::{BlockReward, EpochView};
 use ckb_rational::RationalU256;
 use ckb_types::{
     bytes::Bytes,
@@ -14,28 +20,38 @@ use indicatif::{ProgressBar, ProgressStyle};
 use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::ops::Add;
 use std::process::exit;
+use std::sync::Arc;
 
 const TOTAL_REWARD: Capacity = capacity_bytes!(18_000_000);
 const THRESHOLD: Capacity = capacity_bytes!(1_000);
 const METRIC_EPOCH: u64 = 4;
 const BYTE_SHANNONS: u64 = 100_000_000;
+/// The cellbase of a block rewards the block 11 blocks before it, so the first 11 blocks have no
+/// rewards.
+const FIRST_REWARD_BLOCK: u64 = 12;
 
 pub struct Explorer {
     rpc: RpcClient,
     target: u64,
+    /// Number of blocks fetched in one batch request.
+    batch_size: u64,
+    /// Number of batch requests in flight.
+    jobs: usize,
 }
 
 impl Explorer {
-    pub fn new(url: &str, target: u64) -> Explorer {
+    pub fn new(url: &str, target: u64, batch_size: u64, jobs: usize) -> Explorer {
         Explorer {
             rpc: RpcClient::new(url),
             target,
+            batch_size,
+            jobs,
         }
     }
 
     pub fn collect(
         &self,
-        map: &mut BTreeMap<Bytes, Capacity>,
+        map: &mut BTreeMap<(CodeHashIndex, Bytes), Capacity>,
     ) -> Result<(u64, u32, Byte32, u64), Error> {
         let tip_header: HeaderView = self.rpc.get_tip_header()?.into();
         let tip_epoch = tip_header.epoch();
@@ -63,50 +79,39 @@ impl Explorer {
                 .progress_chars("##-"),
         );
 
-        for num in 1..=11 {
-            progress_bar.inc(1);
-            if let Some(block) = self.rpc.get_block_by_number(num.into())? {
-                let block: BlockView = block.into();
+        let rpc = Arc::new(RpcClient::new(self.rpc.url.as_str()));
+        let fetcher = Fetcher::new(
+            ranges(1, endpoint + 11, self.batch_size),
+            self.jobs,
+            move |start, end| fetch_blocks(&rpc, start, end),
+        );
+        for blocks in fetcher {
+            for (cursor, block, reward) in blocks? {
+                progress_bar.inc(1);
                 windows.push_back(block);
-            } else {
-                exit(1);
-            }
-        }
+                // the first 11 blocks only fill the window
+                let reward = match reward {
+                    Some(reward) => reward,
+                    None => continue,
+                };
 
-        for cursor in 12..=(endpoint + 11) {
-            progress_bar.inc(1);
-            if let Some(block) = self.rpc.get_block_by_number(cursor.into())? {
-                let block: BlockView = block.into();
-                windows.push_back(block);
-            } else {
-                exit(1);
-            }
+                let target_lock = CellbaseWitness::from_slice(
+                    &windows[0].transactions()[0]
+                        .witnesses()
+                        .get(0)
+                        .expect("target witness exist")
+                        .raw_data(),
+                )
+                .expect("cellbase loaded from store should has non-empty witness")
+                .lock();
 
-            let hash = self
-                .rpc
-                .get_block_hash(cursor.into())?
-                .unwrap_or_else(|| exit(1));
-
-            let reward = self
-                .rpc
-                .get_cellbase_output_capacity_details(hash)?
-                .unwrap_or_else(|| exit(1));
-            let target_lock = CellbaseWitness::from_slice(
-                &windows[0].transactions()[0]
-                    .witnesses()
-                    .get(0)
-                    .expect("target witness exist")
-                    .raw_data(),
-            )
-            .expect("cellbase loaded from store should has non-empty witness")
-            .lock();
-
-            let entry = rewards.entry(target_lock).or_insert_with(Capacity::zero);
-            let primary: u64 = reward.primary.into();
-
-            *entry = entry.safe_add(primary)?;
-            if cursor != endpoint + 11 {
-                windows.pop_front();
+                let entry = rewards.entry(target_lock).or_insert_with(Capacity::zero);
+                let primary: u64 = reward.primary.into();
+
+                *entry = entry.safe_add(primary)?;
+                if cursor != endpoint + 11 {
+                    windows.pop_front();
+                }
             }
         }
         let chosen_one = windows.pop_front().unwrap_or_else(|| exit(1));
@@ -124,25 +129,13 @@ impl Explorer {
             let reward = (get_low64(&(total * ratio).into_u256()) / BYTE_SHANNONS) * BYTE_SHANNONS;
 
             let entry = map
-                .entry(lock.args().raw_data())
+                .entry((CodeHashIndex::Sighash, lock.args().raw_data()))
                 .or_insert_with(Capacity::zero);
             *entry = entry.safe_add(reward)?;
         }
 
-        let epochs: Vec<_> = (0..METRIC_EPOCH)
-            .map(|i| {
-                self.rpc
-                    .get_epoch_by_number((self.target - i).into())
-                    .unwrap_or_else(|_| exit(1))
-                    .unwrap_or_else(|| exit(1))
-            })
-            .collect();
-
-        let avg_diff: U256 = epochs
-            .iter()
-            .map(|epoch| compact_to_difficulty(epoch.compact_target.into()))
-            .fold(U256::zero(), U256::add)
-            / U256::from(METRIC_EPOCH);
+        let epochs = self.metric_epochs()?;
+        let avg_diff = average_difficulty(&epochs);
 
         let diff = (avg_diff * U256::from(3u64) / U256::from(2u64)) * U256::from(total.as_u64())
             / U256::from(TOTAL_REWARD.as_u64());
@@ -165,25 +158,10 @@ impl Explorer {
         let avg_epoch_duration = if tip_epoch.number() < METRIC_EPOCH {
             4 * 3600
         } else {
-            // get average elapsed time in the last four full epochs
-            let first_epoch = self
-                .rpc
-                .get_epoch_by_number((tip_epoch.number() - METRIC_EPOCH).into())
-                .unwrap_or_else(|_| exit(1))
-                .unwrap_or_else(|| exit(1));
-            let first_block = self
-                .rpc
-                .get_header_by_number(first_epoch.start_number.into())?
-                .unwrap_or_else(|| exit(1));
-            let last_block = self
-                .rpc
-                .get_header_by_number(
-                    (Into::<u64>::into(tip_header.number()) - tip_epoch.index()).into(),
-                )?
-                .unwrap_or_else(|| exit(1));
-            let t1: u64 = first_block.inner.timestamp.into();
-            let t2: u64 = last_block.inner.timestamp.into();
-            (t2 - t1) / METRIC_EPOCH / 1000
+            self.measure_epoch_duration(
+                tip_epoch.number(),
+                tip_header.number() - tip_epoch.index(),
+            )?
         };
 
         let remaining_seconds = (self.target - tip_epoch.number()) * avg_epoch_duration
@@ -209,6 +187,109 @@ impl Explorer {
 
         Ok(())
     }
+
+    /// The epoch duration in seconds to convert lock dates with. `compact_target` is the genesis
+    /// compact target, which is higher than the testnet difficulty, so the projected duration
+    /// is the measured one scaled by the ratio of the difficulties.
+    pub fn calibrate(&self, calibration: Calibration, compact_target: u32) -> Result<u64, Error> {
+        let next_epoch = self
+            .rpc
+            .get_epoch_by_number((self.target + 1).into())?
+            .ok_or_else(|| not_found("epoch", self.target + 1))?;
+        let measured =
+            self.measure_epoch_duration(self.target + 1, next_epoch.start_number.into())?;
+        match calibration {
+            Calibration::Measured => Ok(measured),
+            Calibration::Projected => {
+                let avg_diff = average_difficulty(&self.metric_epochs()?);
+                let diff = compact_to_difficulty(compact_target);
+                Ok(get_low64(&(U256::from(measured) * diff / avg_diff)))
+            }
+        }
+    }
+
+    /// Average elapsed seconds in the `METRIC_EPOCH` full epochs before epoch `end`, which starts
+    /// at block `end_start`.
+    fn measure_epoch_duration(&self, end: u64, end_start: u64) -> Result<u64, Error> {
+        let first_epoch = self
+            .rpc
+            .get_epoch_by_number((end - METRIC_EPOCH).into())?
+            .ok_or_else(|| not_found("epoch", end - METRIC_EPOCH))?;
+        let first_start: u64 = first_epoch.start_number.into();
+        let first_block = self
+            .rpc
+            .get_header_by_number(first_start.into())?
+            .ok_or_else(|| not_found("block", first_start))?;
+        let last_block = self
+            .rpc
+            .get_header_by_number(end_start.into())?
+            .ok_or_else(|| not_found("block", end_start))?;
+        let t1: u64 = first_block.inner.timestamp.into();
+        let t2: u64 = last_block.inner.timestamp.into();
+        Ok((t2 - t1) / METRIC_EPOCH / 1000)
+    }
+
+    /// The last `METRIC_EPOCH` epochs until the target epoch, the latest first.
+    fn metric_epochs(&self) -> Result<Vec<EpochView>, Error> {
+        (0..METRIC_EPOCH)
+            .map(|i| {
+                self.rpc
+                    .get_epoch_by_number((self.target - i).into())?
+                    .ok_or_else(|| not_found("epoch", self.target - i))
+            })
+            .collect()
+    }
+}
+
+/// Fetches the blocks from `start` to `end` in batch requests, with the rewards in their cellbases
+/// from `FIRST_REWARD_BLOCK` on.
+fn fetch_blocks(
+    rpc: &RpcClient,
+    start: u64,
+    end: u64,
+) -> Result<Vec<(u64, BlockView, Option<BlockReward>)>, Error> {
+    let numbers: Vec<_> = (start..=end).map(|number| (number.into(),)).collect();
+    let blocks = rpc.get_block_by_number_batch(numbers)?;
+    let rewarded = start.max(FIRST_REWARD_BLOCK);
+    let numbers: Vec<_> = (rewarded..=end).map(|number| (number.into(),)).collect();
+    let hashes = rpc
+        .get_block_hash_batch(numbers)?
+        .into_iter()
+        .zip(rewarded..)
+        .map(|(hash, number)| {
+            hash.map(|hash| (hash,))
+                .ok_or_else(|| not_found("block", number))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut rewards = rpc
+        .get_cellbase_output_capacity_details_batch(hashes)?
+        .into_iter();
+
+    (start..=end)
+        .zip(blocks)
+        .map(|(number, block)| {
+            let block = block.ok_or_else(|| not_found("block", number))?;
+            let reward = if number < FIRST_REWARD_BLOCK {
+                None
+            } else {
+                let reward = rewards.next().and_then(|reward| reward);
+                Some(reward.ok_or_else(|| not_found("cellbase reward of block", number))?)
+            };
+            Ok((number, block.into(), reward))
+        })
+        .collect()
+}
+
+fn not_found(what: &str, number: u64) -> Error {
+    failure::err_msg(format!("{} {} not found", what, number))
+}
+
+fn average_difficulty(epochs: &[EpochView]) -> U256 {
+    epochs
+        .iter()
+        .map(|epoch| compact_to_difficulty(epoch.compact_target.into()))
+        .fold(U256::zero(), U256::add)
+        / U256::from(epochs.len() as u64)
 }
 
 fn get_low64(u256: &U256) -> u64 {
@@ -0,0 +1,209 @@
+use crate::address::{Address, NetworkType};
+use crate::manifest::{Manifest, PartitionKind};
+use crate::template::IssuedCell;
+use ckb_types::prelude::*;
+use failure::Error;
+use serde_derive::Serialize;
+use std::fs::File;
+
+/// An issued cell with its lock script and lock hash.
+#[derive(Debug, Serialize)]
+pub struct LockHashRecord {
+    pub partition: String,
+    /// The address in the input, the testnet address of a miner, or the mainnet address of a lock
+    /// script given in columns.
+    pub address: String,
+    pub capacity: u64,
+    pub code_hash: String,
+    pub hash_type: String,
+    pub args: String,
+    pub lock_hash: String,
+    /// The lock as given in the input, or in the manifest for the foundation reserve.
+    pub lock: Option<String>,
+    /// Unlock time in UTC, empty unless the lock is given as a time or a timestamp since.
+    pub unlock_time: Option<String>,
+}
+
+/// Computes the lock hashes of the issued cells, in the order they are issued.
+pub fn lock_hash_records(
+    manifest: &Manifest,
+    allocate: &[(String, Vec<IssuedCell>)],
+    foundation_reserve: &IssuedCell,
+    testnet_incentives: &[IssuedCell],
+) -> Result<Vec<LockHashRecord>, Error> {
+    let mut records = vec![];
+    for (file, cells) in allocate {
+        let partitions: Vec<_> = manifest
+            .partitions
+            .iter()
+            .filter(|p| p.kind == PartitionKind::Allocate && p.sources.contains(file))
+            .map(|p| p.name.as_str())
+            .collect();
+        for cell in cells {
+            let partition = match cell.category {
+                Some(ref category) => category.clone(),
+                None => partitions.join("|"),
+            };
+            records.push(lock_hash_record(partition, cell)?);
+        }
+    }
+
+    let foundation = manifest.partition(PartitionKind::Foundation)?;
+    records.push(lock_hash_record(
+        foundation.name.clone(),
+        foundation_reserve,
+    )?);
+    let incentives = manifest.partition(PartitionKind::Incentives)?;
+    for cell in testnet_incentives {
+        records.push(lock_hash_record(incentives.name.clone(), cell)?);
+    }
+    Ok(records)
+}
+
+fn lock_hash_record(partition: String, cell: &IssuedCell) -> Result<LockHashRecord, Error> {
+    let address = match cell.address {
+        Some(ref address) => address.clone(),
+        None => Address::from_script(&cell.lock, NetworkType::Mainnet)?.to_string(),
+    };
+    Ok(LockHashRecord {
+        partition,
+        address,
+        capacity: cell.capacity,
+        code_hash: cell.code_hash.clone(),
+        hash_type: cell.hash_type.clone(),
+        args: cell.args.clone(),
+        lock_hash: format!(
+            "0x{}",
+            faster_hex::hex_string(cell.lock.calc_script_hash().as_slice()).unwrap()
+        ),
+        lock: cell.lock_input.clone(),
+        unlock_time: cell.unlock_time.clone(),
+    })
+}
+
+/// Writes the records as CSV, or as JSON if the path ends with ".json".
+pub fn write_lock_hashes(path: &str, records: &[LockHashRecord]) -> Result<(), Error> {
+    if path.ends_with(".json") {
+        std::fs::write(path, serde_json::to_string_pretty(records)?)?;
+        return Ok(());
+    }
+    let mut writer = csv::Writer::from_writer(File::create(path)?);
+    for record in records {
+        writer.serialize(record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::{short_lock, CodeHashIndex};
+    use crate::manifest::InputDir;
+    use ckb_types::packed::Script;
+    use std::fs;
+
+    const ADDRESS: &str = "ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq";
+    const TESTNET_ADDRESS: &str = "ckt1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xks5mjl7u";
+    const LOCK_HASH: &str = "0x7c7232c0af4a7261674a45e14916f926ecec64b911f539e573fb4bb7817d001e";
+
+    fn records() -> Vec<LockHashRecord> {
+        let (manifest, _) = InputDir::Embedded.manifest().unwrap();
+        let address = Address::from_str_on(ADDRESS, NetworkType::Mainnet).unwrap();
+        let allocate = vec![(
+            "genesis_final.csv".to_string(),
+            vec![
+                IssuedCell {
+                    address: Some(ADDRESS.to_string()),
+                    category: Some("team".to_string()),
+                    ..IssuedCell::new(100, Script::from(&address))
+                },
+                IssuedCell::new(200, short_lock(CodeHashIndex::Multisig, address.args())),
+            ],
+        )];
+        let foundation_reserve = IssuedCell {
+            address: Some(ADDRESS.to_string()),
+            lock_input: Some("2020-07-01T08:00:00 Asia/Shanghai".to_string()),
+            unlock_time: Some("2020-07-01T00:00:00Z".to_string()),
+            ..IssuedCell::new(300, short_lock(CodeHashIndex::Multisig, address.args()))
+        };
+        let testnet_incentives = vec![IssuedCell {
+            address: Some(TESTNET_ADDRESS.to_string()),
+            ..IssuedCell::new(400, Script::from(&address))
+        }];
+        lock_hash_records(
+            &manifest,
+            &allocate,
+            &foundation_reserve,
+            &testnet_incentives,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_lock_hash_records() {
+        let records = records();
+        let exported: Vec<_> = records
+            .iter()
+            .map(|record| (record.partition.as_str(), record.address.as_str()))
+            .collect();
+        assert_eq!(
+            exported,
+            [
+                ("team", ADDRESS),
+                (
+                    "public-sale|ecosystem|team|private-sale|strategic-partners",
+                    "ckb1qyql5wh6yy6rr8u5w88jzqj0qv5rr0zx2xksaja5a5"
+                ),
+                ("foundation-reserve", ADDRESS),
+                ("testnet-incentives", TESTNET_ADDRESS),
+            ]
+        );
+
+        // the sighash lock of ADDRESS
+        assert_eq!(records[0].lock_hash, LOCK_HASH);
+        assert_eq!(records[3].lock_hash, LOCK_HASH);
+        assert_eq!(
+            records[0].args,
+            "0xfa3afa2134319f9471cf21024f032831bc4651ad"
+        );
+        assert_ne!(records[1].lock_hash, LOCK_HASH);
+    }
+
+    #[test]
+    fn test_write_lock_hashes() {
+        let records = records();
+        let path = std::env::temp_dir().join(format!("ckb-gbg-{}.lock_hashes", std::process::id()));
+
+        let csv_path = format!("{}.csv", path.display());
+        write_lock_hashes(&csv_path, &records).unwrap();
+        let csv = fs::read_to_string(&csv_path).unwrap();
+        fs::remove_file(&csv_path).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[0],
+            "partition,address,capacity,code_hash,hash_type,args,lock_hash,lock,unlock_time"
+        );
+        assert_eq!(
+            lines[1],
+            format!(
+                "team,{},100,0x9bd7e06f3ecf4be0f2fcd2188b23f1b9fcc88e5d4b65a8637b17723bbda3cce8,\
+                 type,0xfa3afa2134319f9471cf21024f032831bc4651ad,{},,",
+                ADDRESS, LOCK_HASH
+            )
+        );
+        assert!(lines[3].ends_with(",2020-07-01T08:00:00 Asia/Shanghai,2020-07-01T00:00:00Z"));
+
+        let json_path = format!("{}.json", path.display());
+        write_lock_hashes(&json_path, &records).unwrap();
+        let json = fs::read_to_string(&json_path).unwrap();
+        fs::remove_file(&json_path).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 4);
+        assert_eq!(json[3]["address"], TESTNET_ADDRESS);
+        assert_eq!(json[3]["lock_hash"], LOCK_HASH);
+        assert_eq!(json[3]["capacity"], 400);
+        assert!(json[3]["unlock_time"].is_null());
+    }
+}
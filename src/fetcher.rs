@@ -0,0 +1,118 @@
+use failure::Error;
+use std::collections::BTreeMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Runs `fetch` over block ranges on worker threads, with at most `jobs` ranges in flight, and
+/// yields the results in the order of the ranges however the responses come back.
+pub struct Fetcher<T> {
+    ranges: Vec<(u64, u64)>,
+    /// Number of ranges sent to the workers.
+    sent: usize,
+    /// Number of results yielded.
+    delivered: usize,
+    /// Results which came back before the ones of the earlier ranges.
+    pending: BTreeMap<usize, Result<T, Error>>,
+    job_sender: Sender<(usize, u64, u64)>,
+    result_receiver: Receiver<(usize, Result<T, Error>)>,
+}
+
+impl<T: Send + 'static> Fetcher<T> {
+    pub fn new<F>(ranges: Vec<(u64, u64)>, jobs: usize, fetch: F) -> Fetcher<T>
+    where
+        F: Fn(u64, u64) -> Result<T, Error> + Send + Sync + 'static,
+    {
+        let (job_sender, job_receiver) = channel::<(usize, u64, u64)>();
+        let (result_sender, result_receiver) = channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let fetch = Arc::new(fetch);
+        for _ in 0..jobs {
+            let job_receiver = Arc::clone(&job_receiver);
+            let result_sender = result_sender.clone();
+            let fetch = Arc::clone(&fetch);
+            // Workers exit once the fetcher is dropped.
+            thread::spawn(move || loop {
+                let job = job_receiver.lock().unwrap().recv();
+                let (i, start, end) = match job {
+                    Ok(job) => job,
+                    Err(_) => break,
+                };
+                if result_sender.send((i, fetch(start, end))).is_err() {
+                    break;
+                }
+            });
+        }
+
+        let mut fetcher = Fetcher {
+            ranges,
+            sent: 0,
+            delivered: 0,
+            pending: BTreeMap::new(),
+            job_sender,
+            result_receiver,
+        };
+        for _ in 0..jobs {
+            fetcher.send_next();
+        }
+        fetcher
+    }
+}
+
+impl<T> Fetcher<T> {
+    fn send_next(&mut self) {
+        if let Some(&(start, end)) = self.ranges.get(self.sent) {
+            self.job_sender
+                .send((self.sent, start, end))
+                .expect("fetcher workers exited");
+            self.sent += 1;
+        }
+    }
+}
+
+impl<T> Iterator for Fetcher<T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.delivered == self.ranges.len() {
+            return None;
+        }
+        while !self.pending.contains_key(&self.delivered) {
+            let (i, result) = self.result_receiver.recv().expect("fetcher workers exited");
+            self.pending.insert(i, result);
+        }
+        let result = self.pending.remove(&self.delivered);
+        self.delivered += 1;
+        self.send_next();
+        result
+    }
+}
+
+/// Splits the blocks from `start` to `end` into ranges of `size` blocks.
+pub fn ranges(start: u64, end: u64, size: u64) -> Vec<(u64, u64)> {
+    (start..=end)
+        .step_by(size as usize)
+        .map(|first| (first, end.min(first + size - 1)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_fetcher() {
+        let ranges = ranges(1, 100, 7);
+        assert_eq!(ranges.len(), 15);
+        assert_eq!(ranges[14], (99, 100));
+
+        // the later ranges come back first
+        let fetcher = Fetcher::new(ranges.clone(), 4, |start, end| {
+            thread::sleep(Duration::from_millis(100 - start));
+            Ok((start, end))
+        });
+        let fetched: Vec<_> = fetcher.map(Result::unwrap).collect();
+        assert_eq!(fetched, ranges);
+    }
+}
@@ -1,132 +1,1377 @@
 use crate::{
-    address::Address,
-    date::{parse_date, Outset},
+    address::{short_lock, Address, AddressPayload, CodeHashIndex, NetworkType},
+    date::{format_time, parse_time, Outset},
+    manifest::InputError,
+    multisig::MultisigConfig,
+    since::{Since, SinceMetric},
     template::IssuedCell,
-    DEFAULT_CODE_HASH, MULTISIG_CODE_HASH,
+    vesting::Schedule,
+};
+use chrono::{offset::TimeZone, DateTime, Utc};
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, EpochNumberWithFraction, ScriptHashType},
+    packed::{CellOutput, Script},
+    prelude::*,
+    H256,
 };
-use ckb_types::{bytes::Bytes, core::Capacity};
 use failure::Error;
-use serde_derive::Deserialize;
-use std::collections::BTreeMap;
-use std::convert::{TryFrom, TryInto};
+use serde::de::{DeserializeOwned, Error as _};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{value::RawValue, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::io::Read;
-use std::process::exit;
+use std::str::FromStr;
 
 const BYTE_SHANNONS: u64 = 100_000_000;
+const BYTE_DECIMALS: usize = 8;
+const SCHEDULE_PREFIX: &str = "schedule:";
+const SIGNER_SEPARATOR: char = ';';
+const RAW_RECORD_FIELDS: &[&str] = &["address", "capacity"];
+const LOCK_RECORD_FIELDS: &[&str] = &[
+    "address",
+    "capacity",
+    "lock",
+    "category",
+    "code_hash",
+    "hash_type",
+    "args",
+    "threshold",
+    "require_first_n",
+];
 
 #[derive(Debug, Deserialize)]
 pub struct RawRecord {
     pub address: String,
-    pub capacity: u64,
+    /// See `parse_capacity`.
+    pub capacity: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct LockRecord {
-    pub address: String,
-    pub capacity: u64,
+    /// Empty when the lock script is given in the `code_hash`, `hash_type` and `args` columns.
+    pub address: Option<String>,
+    /// See `parse_capacity`.
+    pub capacity: String,
     pub lock: Option<String>,
+    /// Name of the partition this row belongs to.
+    pub category: Option<String>,
+    /// In hex with the 0x prefix.
+    pub code_hash: Option<String>,
+    /// "data" or "type".
+    pub hash_type: Option<String>,
+    /// In hex with the 0x prefix, default to empty.
+    pub args: Option<String>,
+    /// M of the multisig lock when the address column lists several signers separated by ";".
+    pub threshold: Option<String>,
+    /// Number of the first signers which must sign, default to 0.
+    pub require_first_n: Option<String>,
 }
 
 pub struct TestnetIncentives {
+    pub index: CodeHashIndex,
     pub args: Bytes,
     pub capacity: Capacity,
 }
 
 pub struct Allocate {
-    pub args: Bytes,
-    pub code_hash: String,
+    pub lock: Script,
     pub capacity: Capacity,
+    /// Unlock time in UTC of a lock given as a time or a timestamp since.
+    pub unlock_time: Option<DateTime<Utc>>,
+}
+
+/// Format of an input file, CSV unless the file name ends with ".json" or ".jsonl".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Csv,
+    /// An array of objects.
+    Json,
+    /// One object per line.
+    JsonLines,
+}
+
+impl InputFormat {
+    pub fn from_file(file: &str) -> InputFormat {
+        if file.ends_with(".json") {
+            InputFormat::Json
+        } else if file.ends_with(".jsonl") {
+            InputFormat::JsonLines
+        } else {
+            InputFormat::Csv
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = InputError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "csv" => Ok(InputFormat::Csv),
+            "json" => Ok(InputFormat::Json),
+            "jsonl" => Ok(InputFormat::JsonLines),
+            _ => Err(InputError(format!(
+                "invalid input format: {}, expect csv, json or jsonl",
+                input
+            ))),
+        }
+    }
+}
+
+/// What to do with allocation rows issued to the same lock, which also means the same lock date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    /// Issue one cell per row.
+    Keep,
+    /// Merge the rows into the cell of the first row.
+    Merge,
+    /// Reject the rows after the first one.
+    Reject,
+}
+
+impl fmt::Display for DuplicatePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DuplicatePolicy::Keep => write!(f, "keep"),
+            DuplicatePolicy::Merge => write!(f, "merge"),
+            DuplicatePolicy::Reject => write!(f, "reject"),
+        }
+    }
 }
 
-pub fn collect_allocate<R: Read>(reader: R, target: u64) -> Vec<IssuedCell> {
-    let mut rdr = csv::ReaderBuilder::new()
-        .has_headers(false)
-        .from_reader(reader);
-    rdr.deserialize()
-        .filter_map(|record: Result<LockRecord, _>| {
+impl FromStr for DuplicatePolicy {
+    type Err = InputError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "keep" => Ok(DuplicatePolicy::Keep),
+            "merge" => Ok(DuplicatePolicy::Merge),
+            "reject" => Ok(DuplicatePolicy::Reject),
+            _ => Err(InputError(format!(
+                "invalid duplicate policy: {}, expect keep, merge or reject",
+                input
+            ))),
+        }
+    }
+}
+
+/// A rejected input row.
+#[derive(Debug)]
+pub struct RowError {
+    pub file: String,
+    pub line: u64,
+    pub column: Option<&'static str>,
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: ", self.file, self.line)?;
+        if let Some(column) = self.column {
+            write!(f, "{}: ", column)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// An error in a single field of a record, located by the caller.
+#[derive(Debug)]
+pub struct FieldError {
+    pub column: &'static str,
+    pub error: Error,
+}
+
+impl FieldError {
+    pub fn new<E: Into<Error>>(column: &'static str, error: E) -> FieldError {
+        FieldError {
+            column,
+            error: error.into(),
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.column, self.error)
+    }
+}
+
+impl RowError {
+    fn from_csv(file: &str, fields: &[&'static str], error: &csv::Error) -> RowError {
+        let line = error.position().map(|pos| pos.line()).unwrap_or(0);
+        let (column, message) = match error.kind() {
+            csv::ErrorKind::Deserialize { err, .. } => (
+                err.field().and_then(|i| fields.get(i as usize)).cloned(),
+                err.kind().to_string(),
+            ),
+            _ => (None, error.to_string()),
+        };
+        RowError {
+            file: file.to_string(),
+            line,
+            column,
+            message,
+        }
+    }
+
+    fn from_json(file: &str, line: u64, error: &serde_json::Error) -> RowError {
+        RowError {
+            file: file.to_string(),
+            line,
+            column: None,
+            message: error.to_string(),
+        }
+    }
+
+    fn from_field(file: &str, line: u64, error: FieldError) -> RowError {
+        RowError {
+            file: file.to_string(),
+            line,
+            column: Some(error.column),
+            message: error.error.to_string(),
+        }
+    }
+}
+
+/// Deserializes every row of `rdr`, recording the rows that fail.
+fn read_csv_records<T: DeserializeOwned, R: Read>(
+    mut rdr: csv::Reader<R>,
+    file: &str,
+    fields: &[&'static str],
+    errors: &mut Vec<RowError>,
+) -> Vec<(u64, T)> {
+    let headers = if rdr.has_headers() {
+        match rdr.headers() {
+            Ok(headers) => Some(headers.clone()),
+            Err(e) => {
+                errors.push(RowError::from_csv(file, fields, &e));
+                return vec![];
+            }
+        }
+    } else {
+        None
+    };
+
+    let mut records = vec![];
+    for result in rdr.records() {
+        let parsed = result.and_then(|record| {
+            let line = record.position().map(|pos| pos.line()).unwrap_or(0);
             record
-                .ok()
-                .and_then(|r| convert_record_allocate(r, target).ok())
+                .deserialize(headers.as_ref())
+                .map(|record| (line, record))
+        });
+        match parsed {
+            Ok(record) => records.push(record),
+            Err(e) => errors.push(RowError::from_csv(file, fields, &e)),
+        }
+    }
+    records
+}
+
+/// Deserializes a JSON array of records. The line of a record is where its value starts.
+fn read_json_records<T: DeserializeOwned>(
+    content: &str,
+    file: &str,
+    errors: &mut Vec<RowError>,
+) -> Vec<(u64, T)> {
+    let values: Vec<&RawValue> = match serde_json::from_str(content) {
+        Ok(values) => values,
+        Err(e) => {
+            errors.push(RowError::from_json(file, e.line() as u64, &e));
+            return vec![];
+        }
+    };
+
+    values
+        .into_iter()
+        .filter_map(|value| {
+            let offset = value.get().as_ptr() as usize - content.as_ptr() as usize;
+            let line = content[..offset].matches('\n').count() as u64 + 1;
+            match deserialize_json_record(value.get()) {
+                Ok(record) => Some((line, record)),
+                Err(e) => {
+                    errors.push(RowError::from_json(file, line, &e));
+                    None
+                }
+            }
         })
-        .map(|record| {
-            let Allocate {
-                args,
-                code_hash,
-                capacity,
-            } = record;
-            IssuedCell {
-                capacity: capacity.as_u64(),
-                code_hash,
-                args: format!("0x{}", faster_hex::hex_string(&args[..]).unwrap()),
+        .collect()
+}
+
+/// Deserializes one JSON record per line, skipping blank lines.
+fn read_json_lines_records<T: DeserializeOwned>(
+    content: &str,
+    file: &str,
+    errors: &mut Vec<RowError>,
+) -> Vec<(u64, T)> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| match deserialize_json_record(line) {
+            Ok(record) => Some((i as u64 + 1, record)),
+            Err(e) => {
+                errors.push(RowError::from_json(file, i as u64 + 1, &e));
+                None
             }
         })
         .collect()
 }
 
-pub fn parse_mining_competition_record<R: Read>(reader: R, map: &mut BTreeMap<Bytes, Capacity>) {
-    let mut rdr = csv::Reader::from_reader(reader);
-    let records: Vec<TestnetIncentives> = rdr
-        .deserialize()
-        .filter_map(|record: Result<RawRecord, _>| record.ok().and_then(|r| r.try_into().ok()))
-        .collect();
+/// Integers are converted to strings and empty strings to null, so JSON records go through the
+/// same parsing as CSV fields. Other numbers are rejected since they may have lost precision.
+fn deserialize_json_record<T: DeserializeOwned>(input: &str) -> Result<T, serde_json::Error> {
+    let value = match serde_json::from_str::<Value>(input)? {
+        Value::Object(map) => {
+            let mut record = Map::new();
+            for (key, value) in map {
+                let value = match value {
+                    Value::Number(ref number) if number.is_f64() => {
+                        return Err(serde_json::Error::custom(format!(
+                            "{} must be a string if it is not an integer",
+                            key
+                        )));
+                    }
+                    Value::Number(number) => Value::String(number.to_string()),
+                    Value::String(ref string) if string.is_empty() => Value::Null,
+                    value => value,
+                };
+                record.insert(key, value);
+            }
+            Value::Object(record)
+        }
+        value => value,
+    };
+    serde_json::from_value(value)
+}
 
-    for record in records {
-        let TestnetIncentives { args, capacity } = record;
-        let entry = map.entry(args.clone()).or_insert_with(Capacity::zero);
+fn read_records<T: DeserializeOwned, R: Read>(
+    mut reader: R,
+    format: InputFormat,
+    csv_builder: &csv::ReaderBuilder,
+    file: &str,
+    fields: &[&'static str],
+    errors: &mut Vec<RowError>,
+) -> Vec<(u64, T)> {
+    if format == InputFormat::Csv {
+        return read_csv_records(csv_builder.from_reader(reader), file, fields, errors);
+    }
 
-        *entry = entry.safe_add(capacity).unwrap_or_else(|e| {
-            eprintln!("Warn: record capacity reduce overflow: {}", e);
-            exit(1);
+    let mut content = String::new();
+    if let Err(e) = reader.read_to_string(&mut content) {
+        errors.push(RowError {
+            file: file.to_string(),
+            line: 0,
+            column: None,
+            message: e.to_string(),
         });
+        return vec![];
+    }
+    match format {
+        InputFormat::Json => read_json_records(&content, file, errors),
+        _ => read_json_lines_records(&content, file, errors),
     }
 }
 
-impl TryFrom<RawRecord> for TestnetIncentives {
-    type Error = Error;
+/// How the rows of an allocation file are converted to issued cells.
+pub struct AllocateContext<'a> {
+    pub format: InputFormat,
+    /// Addresses must belong to this network.
+    pub network: NetworkType,
+    /// Maps the partitions which may appear in the category column to their labels.
+    pub categories: BTreeMap<String, String>,
+    pub schedules: &'a BTreeMap<String, Schedule>,
+    pub outset: Outset,
+    pub target: u64,
+}
 
-    fn try_from(record: RawRecord) -> Result<Self, Self::Error> {
-        let address = Address::from_str(&record.address)?;
-        Ok(TestnetIncentives {
-            args: address.args,
-            capacity: Capacity::shannons(record.capacity * BYTE_SHANNONS),
+pub fn collect_allocate<R: Read>(
+    reader: R,
+    file: &str,
+    context: &AllocateContext,
+    errors: &mut Vec<RowError>,
+) -> Vec<IssuedCell> {
+    let categories = &context.categories;
+    let mut csv_builder = csv::ReaderBuilder::new();
+    csv_builder.has_headers(false).flexible(true);
+    read_records(
+        reader,
+        context.format,
+        &csv_builder,
+        file,
+        LOCK_RECORD_FIELDS,
+        errors,
+    )
+    .into_iter()
+    .filter_map(|(line, record): (u64, LockRecord)| {
+        let address = record.address.clone();
+        let lock_input = record.lock.clone();
+        let converted = check_category(&record, categories).and_then(|category| {
+            convert_record_allocate(record, context).map(|allocate| (allocate, category))
+        });
+        match converted {
+            Ok((allocate, category)) => Some((line, allocate, category, address, lock_input)),
+            Err(e) => {
+                errors.push(RowError::from_field(file, line, e));
+                None
+            }
+        }
+    })
+    .flat_map(|(line, tranches, category, address, lock_input)| {
+        tranches.into_iter().map(move |tranche| {
+            (
+                line,
+                tranche,
+                category.clone(),
+                address.clone(),
+                lock_input.clone(),
+            )
         })
+    })
+    .map(|(line, record, category, address, lock_input)| {
+        let Allocate {
+            lock,
+            capacity,
+            unlock_time,
+        } = record;
+        IssuedCell {
+            address,
+            lock_input,
+            unlock_time: unlock_time.as_ref().map(format_time),
+            line: Some(line),
+            label: category
+                .as_ref()
+                .and_then(|category| categories.get(category))
+                .cloned(),
+            category,
+            ..IssuedCell::new(capacity.as_u64(), lock)
+        }
+    })
+    .collect()
+}
+
+/// A row issued to the same lock as an earlier row.
+pub struct Duplicate {
+    pub file: String,
+    pub line: u64,
+    pub first_file: String,
+    pub first_line: u64,
+}
+
+impl fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{} duplicates {}:{}",
+            self.file, self.line, self.first_file, self.first_line
+        )
     }
 }
 
+/// Finds the allocation cells issued to the same lock, and applies `policy` to them. Cells of
+/// different categories are never merged. The cells keep their original order.
+pub fn check_duplicates(
+    allocate: &mut Vec<(String, Vec<IssuedCell>)>,
+    policy: DuplicatePolicy,
+    errors: &mut Vec<RowError>,
+) -> Vec<Duplicate> {
+    let mut firsts = HashMap::new();
+    let mut pairs = vec![];
+    for (i, (_, cells)) in allocate.iter().enumerate() {
+        for (j, cell) in cells.iter().enumerate() {
+            let key = (
+                cell.code_hash.clone(),
+                cell.hash_type.clone(),
+                cell.args.clone(),
+            );
+            match firsts.get(&key) {
+                Some(&first) => pairs.push((first, (i, j))),
+                None => {
+                    firsts.insert(key, (i, j));
+                }
+            }
+        }
+    }
+
+    let locate = |(i, j): (usize, usize)| {
+        let (file, cells): &(String, Vec<IssuedCell>) = &allocate[i];
+        (file.clone(), cells[j].line.unwrap_or(0))
+    };
+    let duplicates: Vec<Duplicate> = pairs
+        .iter()
+        .map(|&(first, duplicate)| {
+            let (first_file, first_line) = locate(first);
+            let (file, line) = locate(duplicate);
+            Duplicate {
+                file,
+                line,
+                first_file,
+                first_line,
+            }
+        })
+        .collect();
+
+    match policy {
+        DuplicatePolicy::Keep => {}
+        DuplicatePolicy::Reject => {
+            for duplicate in &duplicates {
+                errors.push(RowError {
+                    file: duplicate.file.clone(),
+                    line: duplicate.line,
+                    column: Some("address"),
+                    message: format!(
+                        "duplicate lock of {}:{}",
+                        duplicate.first_file, duplicate.first_line
+                    ),
+                });
+            }
+        }
+        DuplicatePolicy::Merge => {
+            let mut merged = vec![];
+            for (&((fi, fj), (di, dj)), duplicate) in pairs.iter().zip(duplicates.iter()) {
+                if allocate[fi].1[fj].category != allocate[di].1[dj].category {
+                    errors.push(RowError {
+                        file: duplicate.file.clone(),
+                        line: duplicate.line,
+                        column: Some("category"),
+                        message: format!(
+                            "cannot merge into {}:{} of another category",
+                            duplicate.first_file, duplicate.first_line
+                        ),
+                    });
+                    continue;
+                }
+                let capacity = allocate[di].1[dj].capacity;
+                match allocate[fi].1[fj].capacity.checked_add(capacity) {
+                    Some(sum) => {
+                        allocate[fi].1[fj].capacity = sum;
+                        merged.push((di, dj));
+                    }
+                    None => errors.push(RowError {
+                        file: duplicate.file.clone(),
+                        line: duplicate.line,
+                        column: Some("capacity"),
+                        message: format!(
+                            "merge overflow into {}:{}",
+                            duplicate.first_file, duplicate.first_line
+                        ),
+                    }),
+                }
+            }
+            for (i, (_, cells)) in allocate.iter_mut().enumerate() {
+                let mut j = 0;
+                cells.retain(|_| {
+                    j += 1;
+                    !merged.contains(&(i, j - 1))
+                });
+            }
+        }
+    }
+
+    duplicates
+}
+
+fn check_category(
+    record: &LockRecord,
+    categories: &BTreeMap<String, String>,
+) -> Result<Option<String>, FieldError> {
+    match record.category {
+        Some(ref category) if !categories.contains_key(category) => Err(FieldError::new(
+            "category",
+            InputError(format!("unknown category: {}", category)),
+        )),
+        ref category => Ok(category.clone()),
+    }
+}
+
+/// Rows whose address is listed in `invalid_locks` are rewards mined by invalid locks. They are
+/// skipped, and their share goes to the testnet incentives lock.
+///
+/// Addresses must belong to `network`.
+pub fn parse_mining_competition_record<R: Read>(
+    reader: R,
+    file: &str,
+    format: InputFormat,
+    network: NetworkType,
+    invalid_locks: &[String],
+    map: &mut BTreeMap<(CodeHashIndex, Bytes), Capacity>,
+    errors: &mut Vec<RowError>,
+) {
+    let csv_builder = csv::ReaderBuilder::new();
+    let records: Vec<(u64, TestnetIncentives)> = read_records(
+        reader,
+        format,
+        &csv_builder,
+        file,
+        RAW_RECORD_FIELDS,
+        errors,
+    )
+    .into_iter()
+    .filter(|(_, record): &(u64, RawRecord)| !invalid_locks.contains(&record.address))
+    .filter_map(
+        |(line, record)| match convert_record_incentives(record, network) {
+            Ok(incentives) => Some((line, incentives)),
+            Err(e) => {
+                errors.push(RowError::from_field(file, line, e));
+                None
+            }
+        },
+    )
+    .collect();
+
+    for (line, record) in records {
+        let TestnetIncentives {
+            index,
+            args,
+            capacity,
+        } = record;
+        let entry = map.entry((index, args)).or_insert_with(Capacity::zero);
+
+        match entry.safe_add(capacity) {
+            Ok(sum) => *entry = sum,
+            Err(e) => errors.push(RowError::from_field(
+                file,
+                line,
+                FieldError::new("capacity", InputError(format!("reduce overflow: {}", e))),
+            )),
+        }
+    }
+}
+
+/// Converts a competition row, whose address must belong to `network`.
+fn convert_record_incentives(
+    record: RawRecord,
+    network: NetworkType,
+) -> Result<TestnetIncentives, FieldError> {
+    let address = Address::from_str_on(&record.address, network)
+        .map_err(|e| FieldError::new("address", e))?;
+    let (index, args) = match address.payload {
+        AddressPayload::Short { index, ref args } => (index, args.clone()),
+        AddressPayload::Full { .. } => {
+            return Err(FieldError::new("address", short_address_required()));
+        }
+    };
+    let capacity = parse_capacity(&record.capacity).map_err(|e| FieldError::new("capacity", e))?;
+    Ok(TestnetIncentives {
+        index,
+        args,
+        capacity,
+    })
+}
+
+/// Parses a capacity in CKBytes, such as "100" or "0.5", or in shannons with the "shannons"
+/// suffix, such as "50000000shannons". Overflow and fractions of a shannon are rejected.
+pub fn parse_capacity(input: &str) -> Result<Capacity, Error> {
+    let input = input.trim();
+    let invalid = || InputError(format!("invalid capacity: {}", input));
+
+    for suffix in &["shannons", "shannon"] {
+        if input.ends_with(suffix) {
+            let shannons = input[..input.len() - suffix.len()].trim();
+            if shannons.is_empty() || !shannons.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid().into());
+            }
+            let shannons = shannons
+                .parse::<u64>()
+                .map_err(|_| InputError(format!("capacity overflow: {}", input)))?;
+            return Ok(Capacity::shannons(shannons));
+        }
+    }
+
+    let ckb = if input.ends_with("CKB") {
+        input[..input.len() - 3].trim()
+    } else {
+        input
+    };
+    let mut parts = ckb.splitn(2, '.');
+    let integer = parts.next().unwrap_or("");
+    let fraction = parts.next().unwrap_or("");
+    if integer.is_empty()
+        || !integer.bytes().all(|b| b.is_ascii_digit())
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid().into());
+    }
+    if fraction.len() > BYTE_DECIMALS && fraction[BYTE_DECIMALS..].bytes().any(|b| b != b'0') {
+        return Err(InputError(format!("capacity below 1 shannon: {}", input)).into());
+    }
+
+    let fraction = format!("{:0<width$}", fraction, width = BYTE_DECIMALS);
+    let shannons = integer
+        .parse::<u64>()
+        .ok()
+        .and_then(|integer| integer.checked_mul(BYTE_SHANNONS))
+        .and_then(|shannons| {
+            fraction[..BYTE_DECIMALS]
+                .parse::<u64>()
+                .ok()
+                .and_then(|fraction| shannons.checked_add(fraction))
+        })
+        .ok_or_else(|| InputError(format!("capacity overflow: {}", input)))?;
+    Ok(Capacity::shannons(shannons))
+}
+
 pub fn blake160(message: &[u8]) -> Bytes {
     Bytes::from(&ckb_hash::blake2b_256(message)[..20])
 }
 
+/// The args of the multisig lock of `config` with `lock`, and the unlock time in UTC if any.
 pub fn serialize_multisig_lock_args(
-    address: &str,
-    date: &str,
+    config: &MultisigConfig,
+    lock: &str,
+    outset: &Outset,
     target: u64,
-) -> Result<Bytes, Error> {
-    let address = Address::from_str(address)?;
-    let dt = parse_date(date)?;
-    let since = Outset.since_epoch(&dt, target);
-    let mut script = Bytes::from(vec![0u8, 0, 1, 1]);
-    script.extend_from_slice(&address.args);
-    let mut args = blake160(&script).to_vec();
+) -> Result<(Bytes, Option<DateTime<Utc>>), Error> {
+    let (since, unlock_time) = lock_since(lock, outset, target)?;
+    Ok((multisig_lock_args(&config.hash(), since), unlock_time))
+}
 
+/// The args of the multisig lock issued for an allocation row with `address` and `lock`, and the
+/// unlock time in UTC if any.
+pub fn serialize_address_lock_args(
+    address: &Address,
+    lock: &str,
+    outset: &Outset,
+    target: u64,
+) -> Result<(Bytes, Option<DateTime<Utc>>), Error> {
+    let multisig_hash = multisig_hash(address).ok_or_else(short_address_required)?;
+    let (since, unlock_time) = lock_since(lock, outset, target)?;
+    Ok((multisig_lock_args(&multisig_hash, since), unlock_time))
+}
+
+/// The multisig script hash of a sighash address as the only signer, or of a multisig address.
+fn multisig_hash(address: &Address) -> Option<Bytes> {
+    match address.payload {
+        AddressPayload::Short {
+            index: CodeHashIndex::Sighash,
+            ref args,
+        } => MultisigConfig::new(vec![args.clone()], 1, 0)
+            .ok()
+            .map(|config| config.hash()),
+        AddressPayload::Short {
+            index: CodeHashIndex::Multisig,
+            ref args,
+        } => Some(args.clone()),
+        AddressPayload::Full { .. } => None,
+    }
+}
+
+/// The since of a lock, which is either a time accepted by `parse_time`, or one of
+/// `epoch:<number>[+<index>/<length>]`, `timestamp:<seconds>` and `block:<number>`, optionally
+/// prefixed with `relative:`. Also returns the unlock time in UTC for times and absolute
+/// timestamps.
+fn lock_since(
+    lock: &str,
+    outset: &Outset,
+    target: u64,
+) -> Result<(u64, Option<DateTime<Utc>>), Error> {
+    let genesis = outset.epoch_date(EpochNumberWithFraction::new(0, 0, 1), target);
+    match Since::parse_lock(lock, &genesis)? {
+        Some(since) => {
+            let unlock_time = match since.metric {
+                SinceMetric::Timestamp(timestamp) if !since.relative => {
+                    let time = Utc.timestamp_opt(timestamp as i64, 0).single();
+                    Some(time.ok_or_else(|| {
+                        InputError(format!("timestamp out of range: {}", timestamp))
+                    })?)
+                }
+                _ => None,
+            };
+            Ok((since.to_raw(), unlock_time))
+        }
+        None => {
+            let time = parse_time(lock)?;
+            Ok((outset.since_epoch(&time, target), Some(time)))
+        }
+    }
+}
+
+fn multisig_lock_args(multisig_hash: &Bytes, since: u64) -> Bytes {
+    let mut args = multisig_hash.to_vec();
     args.extend(since.to_le_bytes().iter());
-    Ok(Bytes::from(args))
+    Bytes::from(args)
 }
 
-pub fn convert_record_allocate(record: LockRecord, target: u64) -> Result<Allocate, Error> {
-    if let Some(ref date) = &record.lock {
-        let args = serialize_multisig_lock_args(&record.address, date, target)?;
-        Ok(Allocate {
-            args,
-            code_hash: MULTISIG_CODE_HASH.to_string(),
-            capacity: Capacity::shannons(record.capacity * BYTE_SHANNONS),
-        })
+fn short_address_required() -> InputError {
+    InputError("expect a short sighash or multisig address".to_string())
+}
+
+/// Converts a row to one cell, or one cell per tranche when the lock column is
+/// `schedule:<name>`. Rows without lock are issued to the lock script of the address, which may
+/// be a full payload address.
+///
+/// Rows listing several signer addresses, or with a threshold, are issued to the M-of-N multisig
+/// lock of the signers.
+///
+/// Rows are rejected if any of their cells cannot hold its own lock.
+pub fn convert_record_allocate(
+    record: LockRecord,
+    context: &AllocateContext,
+) -> Result<Vec<Allocate>, FieldError> {
+    let column = match record.lock {
+        Some(ref lock) if lock.starts_with(SCHEDULE_PREFIX) => "lock",
+        _ => "capacity",
+    };
+    let allocate = convert_allocate(record, context)?;
+    for cell in &allocate {
+        let occupied = CellOutput::new_builder()
+            .lock(cell.lock.clone())
+            .build()
+            .occupied_capacity(Capacity::zero())
+            .map_err(|e| FieldError::new(column, e))?;
+        if cell.capacity < occupied {
+            let cell_name = match cell.unlock_time {
+                Some(ref time) if column == "lock" => {
+                    format!("tranche unlocking at {}", format_time(time))
+                }
+                _ => "cell".to_string(),
+            };
+            return Err(FieldError::new(
+                column,
+                InputError(format!(
+                    "{} has {} shannons, less than the {} shannons occupied by its lock",
+                    cell_name,
+                    cell.capacity.as_u64(),
+                    occupied.as_u64()
+                )),
+            ));
+        }
+    }
+    Ok(allocate)
+}
+
+fn convert_allocate(
+    record: LockRecord,
+    context: &AllocateContext,
+) -> Result<Vec<Allocate>, FieldError> {
+    let capacity = parse_capacity(&record.capacity).map_err(|e| FieldError::new("capacity", e))?;
+    if let Some(allocate) = convert_lock_script(&record, capacity)? {
+        if record.address.is_some() {
+            return Err(FieldError::new(
+                "address",
+                InputError("address and lock script columns are exclusive".to_string()),
+            ));
+        }
+        if record.lock.is_some() {
+            return Err(FieldError::new("lock", short_address_required()));
+        }
+        return Ok(vec![allocate]);
+    }
+    let address = record
+        .address
+        .as_ref()
+        .ok_or_else(|| FieldError::new("address", InputError("missing address".to_string())))?;
+    let signers: Vec<&str> = address.split(SIGNER_SEPARATOR).map(str::trim).collect();
+    let (unlocked, multisig) = if signers.len() > 1
+        || record.threshold.is_some()
+        || record.require_first_n.is_some()
+    {
+        let threshold = match record.threshold {
+            Some(ref threshold) => parse_u8(threshold, "threshold")?,
+            None => {
+                return Err(FieldError::new(
+                    "threshold",
+                    InputError("missing threshold".to_string()),
+                ));
+            }
+        };
+        let require_first_n = match record.require_first_n {
+            Some(ref require_first_n) => parse_u8(require_first_n, "require_first_n")?,
+            None => 0,
+        };
+        let hash =
+            MultisigConfig::from_addresses(&signers, context.network, threshold, require_first_n)
+                .map_err(|e| FieldError::new("address", e))?
+                .hash();
+        let unlocked = Allocate {
+            lock: short_lock(CodeHashIndex::Multisig, &hash),
+            capacity,
+            unlock_time: None,
+        };
+        (unlocked, Some(hash))
     } else {
-        let address = Address::from_str(&record.address)?;
-        Ok(Allocate {
-            args: address.args,
-            code_hash: DEFAULT_CODE_HASH.to_string(),
-            capacity: Capacity::shannons(record.capacity * BYTE_SHANNONS),
-        })
+        let address = Address::from_str_on(address, context.network)
+            .map_err(|e| FieldError::new("address", e))?;
+        let unlocked = Allocate {
+            lock: Script::from(&address),
+            capacity,
+            unlock_time: None,
+        };
+        (unlocked, multisig_hash(&address))
+    };
+    let lock_hash = || {
+        multisig
+            .clone()
+            .ok_or_else(|| FieldError::new("lock", short_address_required()))
+    };
+    match record.lock {
+        Some(ref lock) if lock.starts_with(SCHEDULE_PREFIX) => {
+            let lock_hash = lock_hash()?;
+            let name = &lock[SCHEDULE_PREFIX.len()..];
+            let tranches = context
+                .schedules
+                .get(name)
+                .ok_or_else(|| InputError(format!("unknown schedule: {}", name)))
+                .map_err(|e| FieldError::new("lock", e))?
+                .expand(capacity)
+                .map_err(|e| FieldError::new("lock", e))?;
+            Ok(tranches
+                .into_iter()
+                .map(|(date, capacity)| Allocate {
+                    lock: short_lock(
+                        CodeHashIndex::Multisig,
+                        &multisig_lock_args(
+                            &lock_hash,
+                            context.outset.since_epoch(&date, context.target),
+                        ),
+                    ),
+                    capacity,
+                    unlock_time: Some(date),
+                })
+                .collect())
+        }
+        Some(ref lock) => {
+            let lock_hash = lock_hash()?;
+            let (since, unlock_time) = lock_since(lock, &context.outset, context.target)
+                .map_err(|e| FieldError::new("lock", e))?;
+            Ok(vec![Allocate {
+                lock: short_lock(
+                    CodeHashIndex::Multisig,
+                    &multisig_lock_args(&lock_hash, since),
+                ),
+                capacity,
+                unlock_time,
+            }])
+        }
+        None => Ok(vec![unlocked]),
+    }
+}
+
+fn parse_u8(input: &str, column: &'static str) -> Result<u8, FieldError> {
+    input
+        .parse()
+        .map_err(|_| FieldError::new(column, InputError(format!("invalid {}: {}", column, input))))
+}
+
+/// Converts the lock script given in the `code_hash`, `hash_type` and `args` columns, if any.
+fn convert_lock_script(
+    record: &LockRecord,
+    capacity: Capacity,
+) -> Result<Option<Allocate>, FieldError> {
+    let (code_hash, hash_type) = match (&record.code_hash, &record.hash_type, &record.args) {
+        (None, None, None) => return Ok(None),
+        (Some(code_hash), Some(hash_type), _) => (code_hash, hash_type),
+        (None, _, _) => {
+            return Err(FieldError::new(
+                "code_hash",
+                InputError("missing code hash".to_string()),
+            ));
+        }
+        (_, None, _) => {
+            return Err(FieldError::new(
+                "hash_type",
+                InputError("missing hash type".to_string()),
+            ));
+        }
+    };
+
+    let code_hash = parse_hex(code_hash)
+        .and_then(|bytes| H256::from_slice(&bytes).ok())
+        .ok_or_else(|| InputError(format!("invalid code hash: {}", code_hash)))
+        .map_err(|e| FieldError::new("code_hash", e))?;
+    let hash_type = match hash_type.as_str() {
+        "data" => ScriptHashType::Data,
+        "type" => ScriptHashType::Type,
+        _ => {
+            return Err(FieldError::new(
+                "hash_type",
+                InputError(format!("invalid hash type: {}", hash_type)),
+            ));
+        }
+    };
+    let args = match record.args {
+        Some(ref args) => parse_hex(args)
+            .ok_or_else(|| InputError(format!("invalid args: {}", args)))
+            .map_err(|e| FieldError::new("args", e))?,
+        None => Bytes::new(),
+    };
+    Ok(Some(Allocate {
+        lock: Script::new_builder()
+            .code_hash(code_hash.pack())
+            .hash_type(hash_type.into())
+            .args(args.pack())
+            .build(),
+        capacity,
+        unlock_time: None,
+    }))
+}
+
+/// Parses hex with the 0x prefix.
+fn parse_hex(input: &str) -> Option<Bytes> {
+    if !input.starts_with("0x") || input.len() % 2 != 0 {
+        return None;
+    }
+    let mut bytes = vec![0; input.len() / 2 - 1];
+    faster_hex::hex_decode(input[2..].as_bytes(), &mut bytes).ok()?;
+    Some(Bytes::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vesting::Period;
+    use lazy_static::lazy_static;
+
+    lazy_static! {
+        static ref SCHEDULES: BTreeMap<String, Schedule> = BTreeMap::new();
+    }
+
+    fn context(format: InputFormat) -> AllocateContext<'static> {
+        AllocateContext {
+            format,
+            network: NetworkType::Mainnet,
+            categories: BTreeMap::new(),
+            schedules: &SCHEDULES,
+            outset: Outset::default(),
+            target: 89,
+        }
+    }
+
+    #[test]
+    fn test_collect_allocate_reports_rows() {
+        let csv = b"ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,100,\"\"
+ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,1x0,\"\"
+ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjX,100,\"\"
+ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,100,2020-13-01
+";
+        let mut errors = vec![];
+        let context = context(InputFormat::Csv);
+        let cells = collect_allocate(&csv[..], "test.csv", &context, &mut errors);
+        assert_eq!(cells.len(), 1);
+        let located: Vec<_> = errors.iter().map(|e| (e.line, e.column)).collect();
+        assert_eq!(
+            located,
+            vec![
+                (2, Some("capacity")),
+                (3, Some("address")),
+                (4, Some("lock"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multisig_address() {
+        let args = parse_hex("0x4146af6d67742cca87a9b0d1d3eb070e7a544e1c").unwrap();
+        let csv = b"ckb1qyqyz340d4nhgtx2s75mp5wnavrsu7j5fcwqktprrp,100,\"\"\n";
+        let mut errors = vec![];
+        let context = context(InputFormat::Csv);
+        let cells = collect_allocate(&csv[..], "test.csv", &context, &mut errors);
+        assert!(errors.is_empty());
+        assert_eq!(cells[0].code_hash, crate::MULTISIG_CODE_HASH);
+        assert_eq!(cells[0].args, "0x4146af6d67742cca87a9b0d1d3eb070e7a544e1c");
+
+        // sighash locks come first
+        let csv = b"address,capacity
+ckt1qyqyz340d4nhgtx2s75mp5wnavrsu7j5fcwqtwlu0a,200
+ckt1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xks5mjl7u,100
+";
+        let mut map = BTreeMap::new();
+        parse_mining_competition_record(
+            &csv[..],
+            "round1.csv",
+            InputFormat::Csv,
+            NetworkType::Testnet,
+            &[],
+            &mut map,
+            &mut errors,
+        );
+        assert!(errors.is_empty());
+        let sighash = parse_hex("0xfa3afa2134319f9471cf21024f032831bc4651ad").unwrap();
+        assert_eq!(
+            map.into_iter().collect::<Vec<_>>(),
+            [
+                (
+                    (CodeHashIndex::Sighash, sighash),
+                    Capacity::shannons(100 * 100_000_000)
+                ),
+                (
+                    (CodeHashIndex::Multisig, args),
+                    Capacity::shannons(200 * 100_000_000)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_network_mismatch() {
+        let csv = b"ckt1qyqdmswal8qn2psmwc6u5508xh7zkq7wuvustsvyew,100,\"\"\n";
+        let mut errors = vec![];
+        let context = context(InputFormat::Csv);
+        let cells = collect_allocate(&csv[..], "test.csv", &context, &mut errors);
+        assert!(cells.is_empty());
+        assert_eq!(
+            errors[0].to_string(),
+            "test.csv:1: address: Expect mainnet address, got testnet address"
+        );
+
+        let csv = b"address,capacity
+ckt1q9gry5zgx5r2xequz62m0rhvy60xvsqj5azl5efd3knr83,200000
+ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,100000
+";
+        let mut errors = vec![];
+        let mut map = BTreeMap::new();
+        parse_mining_competition_record(
+            &csv[..],
+            "round1.csv",
+            InputFormat::Csv,
+            NetworkType::Testnet,
+            &[],
+            &mut map,
+            &mut errors,
+        );
+        assert_eq!(map.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "round1.csv:3: address: Expect testnet address, got mainnet address"
+        );
+    }
+
+    #[test]
+    fn test_occupied_capacity() {
+        let schedule = |cliff_share: &str| Schedule {
+            cliff: "2020-07-01".to_string(),
+            cliff_share: cliff_share.to_string(),
+            period: Period::Yearly,
+            periods: 2,
+        };
+        let mut schedules = BTreeMap::new();
+        schedules.insert("half".to_string(), schedule("50%"));
+        schedules.insert("none".to_string(), schedule("0%"));
+        let csv = b"ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,276,schedule:half
+ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,200,schedule:half
+ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,1000,schedule:none
+ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,0.5,\"\"
+";
+        let context = AllocateContext {
+            schedules: &schedules,
+            ..context(InputFormat::Csv)
+        };
+        let mut errors = vec![];
+        let cells = collect_allocate(&csv[..], "test.csv", &context, &mut errors);
+        assert_eq!(cells.len(), 3);
+        let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            [
+                "test.csv:2: lock: tranche unlocking at 2021-07-01T00:00:00Z has 5000000000 \
+                 shannons, less than the 6900000000 shannons occupied by its lock",
+                "test.csv:3: lock: tranche unlocking at 2020-07-01T00:00:00Z has 0 shannons, \
+                 less than the 6900000000 shannons occupied by its lock",
+                "test.csv:4: capacity: cell has 50000000 shannons, less than the 6100000000 \
+                 shannons occupied by its lock",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_duplicates() {
+        let first = b"ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,100,\"\"
+ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,200,2020-07-01
+";
+        let second = b"ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,70,2020-07-01
+ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,80,2021-07-01
+ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,90,\"\"
+";
+        let third = b"ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,65,\"\",team\n";
+        let context = AllocateContext {
+            categories: vec![("team".to_string(), "Team".to_string())]
+                .into_iter()
+                .collect(),
+            ..context(InputFormat::Csv)
+        };
+        let check = |policy| {
+            let mut errors = vec![];
+            let mut allocate = vec![
+                (
+                    "first.csv".to_string(),
+                    collect_allocate(&first[..], "first.csv", &context, &mut errors),
+                ),
+                (
+                    "second.csv".to_string(),
+                    collect_allocate(&second[..], "second.csv", &context, &mut errors),
+                ),
+                (
+                    "third.csv".to_string(),
+                    collect_allocate(&third[..], "third.csv", &context, &mut errors),
+                ),
+            ];
+            let duplicates: Vec<_> = check_duplicates(&mut allocate, policy, &mut errors)
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            let cells: Vec<Vec<(u64, Option<u64>)>> = allocate
+                .iter()
+                .map(|(_, cells)| {
+                    cells
+                        .iter()
+                        .map(|cell| (cell.capacity / 100_000_000, cell.line))
+                        .collect()
+                })
+                .collect();
+            let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+            (duplicates, cells, errors)
+        };
+
+        // the row locked until 2021-07-01 is issued to another lock
+        let (duplicates, cells, errors) = check(DuplicatePolicy::Keep);
+        assert_eq!(
+            duplicates,
+            [
+                "second.csv:1 duplicates first.csv:2",
+                "second.csv:3 duplicates first.csv:1",
+                "third.csv:1 duplicates first.csv:1"
+            ]
+        );
+        assert_eq!(
+            cells,
+            [
+                vec![(100, Some(1)), (200, Some(2))],
+                vec![(70, Some(1)), (80, Some(2)), (90, Some(3))],
+                vec![(65, Some(1))]
+            ]
+        );
+        assert!(errors.is_empty());
+
+        // the row of another category is not merged
+        let (duplicates, cells, errors) = check(DuplicatePolicy::Merge);
+        assert_eq!(duplicates.len(), 3);
+        assert_eq!(
+            cells,
+            [
+                vec![(190, Some(1)), (270, Some(2))],
+                vec![(80, Some(2))],
+                vec![(65, Some(1))]
+            ]
+        );
+        assert_eq!(
+            errors,
+            ["third.csv:1: category: cannot merge into first.csv:1 of another category"]
+        );
+
+        let (duplicates, cells, errors) = check(DuplicatePolicy::Reject);
+        assert_eq!(duplicates.len(), 3);
+        assert_eq!(cells[1].len(), 3);
+        assert_eq!(
+            errors,
+            [
+                "second.csv:1: address: duplicate lock of first.csv:2",
+                "second.csv:3: address: duplicate lock of first.csv:1",
+                "third.csv:1: address: duplicate lock of first.csv:1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_capacity() {
+        let shannons = |input| parse_capacity(input).map(|c| c.as_u64()).ok();
+        assert_eq!(shannons("11200000"), Some(1_120_000_000_000_000));
+        assert_eq!(shannons("0.5"), Some(50_000_000));
+        assert_eq!(shannons("1.00000001 CKB"), Some(100_000_001));
+        assert_eq!(shannons("1.000000010"), Some(100_000_001));
+        assert_eq!(shannons("61shannons"), Some(61));
+        assert_eq!(shannons("1.000000001"), None);
+        assert_eq!(shannons("184467440738"), None);
+        assert_eq!(shannons("18446744073709551616shannons"), None);
+        assert_eq!(shannons("-1"), None);
+        assert_eq!(shannons("1.5shannons"), None);
+        assert_eq!(shannons(""), None);
+    }
+
+    #[test]
+    fn test_lock_since() {
+        let outset = Outset::default();
+        let (_, unlock_time) = lock_since("timestamp:1593561600", &outset, 89).unwrap();
+        assert_eq!(
+            unlock_time.as_ref().map(format_time),
+            Some("2020-07-01T00:00:00Z".to_string())
+        );
+        assert!(lock_since("timestamp:72057594037927935", &outset, 89).is_err());
+    }
+
+    #[test]
+    fn test_json_records() {
+        let json = br#"[
+  {
+    "address": "ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq",
+    "capacity": 11200000,
+    "lock": ""
+  },
+  {
+    "address": "ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq",
+    "capacity": "1x0"
+  }
+]"#;
+        let jsonl =
+            br#"{"address": "ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq", "capacity": 11200000}
+
+{"address": "ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq", "capacity": "1x0"}
+"#;
+        let csv = b"ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,11200000,\"\"\n";
+
+        let mut errors = vec![];
+        let collect = |input: &[u8], format, errors: &mut Vec<RowError>| {
+            collect_allocate(input, "test", &context(format), errors)
+        };
+        let from_json = collect(&json[..], InputFormat::Json, &mut errors);
+        let from_jsonl = collect(&jsonl[..], InputFormat::JsonLines, &mut errors);
+        let from_csv = collect(&csv[..], InputFormat::Csv, &mut errors);
+
+        let args = |cells: &[IssuedCell]| -> Vec<(u64, String)> {
+            cells.iter().map(|c| (c.capacity, c.args.clone())).collect()
+        };
+        assert_eq!(args(&from_json), args(&from_csv));
+        assert_eq!(args(&from_jsonl), args(&from_csv));
+        let lines: Vec<_> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![7, 3]);
+    }
+
+    #[test]
+    fn test_json_fractional_capacity() {
+        let jsonl = br#"{"address": "ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq", "capacity": 123456789.12345678}
+{"address": "ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq", "capacity": "123456789.12345678"}
+"#;
+        let mut errors = vec![];
+        let context = context(InputFormat::JsonLines);
+        let cells = collect_allocate(&jsonl[..], "test.jsonl", &context, &mut errors);
+
+        assert_eq!(cells.len(), 1);
+        assert_eq!(cells[0].capacity, 12_345_678_912_345_678);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 1);
+        assert!(errors[0]
+            .message
+            .starts_with("capacity must be a string if it is not an integer"));
+    }
+
+    #[test]
+    fn test_lock_script_columns() {
+        let csv = b",100,,,0x9bd7e06f3ecf4be0f2fcd2188b23f1b9fcc88e5d4b65a8637b17723bbda3cce8,type,0xfa3afa2134319f9471cf21024f032831bc4651ad
+ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,100
+,100,,,0x01,data,0x
+ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq,100,,,0x9bd7e06f3ecf4be0f2fcd2188b23f1b9fcc88e5d4b65a8637b17723bbda3cce8,type
+";
+        let mut errors = vec![];
+        let context = context(InputFormat::Csv);
+        let cells = collect_allocate(&csv[..], "test.csv", &context, &mut errors);
+
+        assert_eq!(cells.len(), 2);
+        assert_eq!(cells[0].code_hash, cells[1].code_hash);
+        assert_eq!(cells[0].hash_type, "type");
+        assert_eq!(cells[0].args, cells[1].args);
+        let reported: Vec<_> = errors.iter().map(|e| (e.line, e.column)).collect();
+        assert_eq!(reported, vec![(3, Some("code_hash")), (4, Some("address"))]);
     }
 }
@@ -0,0 +1,155 @@
+use crate::address::{hash_type_name, multisig_code_hash, Address, AddressPayload, CodeHashIndex};
+use crate::date::{format_time, Outset};
+use crate::input::serialize_address_lock_args;
+use crate::since::{Since, SinceMetric};
+use chrono::{offset::TimeZone, Duration, Utc};
+use ckb_types::{
+    core::{EpochNumberWithFraction, ScriptHashType},
+    packed::Script,
+    prelude::*,
+};
+use failure::Error;
+use std::time::Duration as StdDuration;
+
+/// Prints the lock script of an address, and the multisig lock issued for it when `lock` is
+/// given.
+pub fn address(input: &str, lock: Option<&str>, outset: &Outset, target: u64) -> Result<(), Error> {
+    let address = Address::from_str(input)?;
+    println!("hrp: {} ({})", address.network.to_prefix(), address.network);
+    match address.payload {
+        AddressPayload::Short { index, .. } => {
+            let name = match index {
+                CodeHashIndex::Sighash => "secp256k1_blake160_sighash_all",
+                CodeHashIndex::Multisig => "secp256k1_blake160_multisig_all",
+            };
+            println!("format type: 0x{:02x} (short)", address.format_type());
+            println!("code hash index: 0x{:02x} ({})", index as u8, name);
+        }
+        AddressPayload::Full { .. } => {
+            println!("format type: 0x{:02x} (full)", address.format_type());
+        }
+    }
+    println!("code hash: {}", address.code_hash());
+    println!("hash type: {}", hash_type_name(address.hash_type()));
+    println!(
+        "args: 0x{}",
+        faster_hex::hex_string(&address.args()[..]).unwrap()
+    );
+    print_script(&Script::from(&address))?;
+
+    if let Some(lock) = lock {
+        let (args, unlock_time) = serialize_address_lock_args(&address, lock, outset, target)?;
+        let mut since = [0u8; 8];
+        since.copy_from_slice(&args[args.len() - 8..]);
+        println!();
+        println!("lock: {} (target epoch {})", lock, target);
+        if let Some(unlock_time) = unlock_time {
+            println!("unlock time: {}", format_time(&unlock_time));
+        }
+        println!(
+            "multisig args: 0x{}",
+            faster_hex::hex_string(&args[..]).unwrap()
+        );
+        let since = u64::from_le_bytes(since);
+        println!("since: 0x{:016x} ({})", since, Since::from_raw(since)?);
+        let script = Script::new_builder()
+            .code_hash(multisig_code_hash().pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(args.pack())
+            .build();
+        print_script(&script)?;
+    }
+    Ok(())
+}
+
+fn print_script(script: &Script) -> Result<(), Error> {
+    let json = ckb_jsonrpc_types::Script::from(script.clone());
+    println!("script: {}", serde_json::to_string_pretty(&json)?);
+    println!(
+        "lock hash: 0x{}",
+        faster_hex::hex_string(script.calc_script_hash().as_slice()).unwrap()
+    );
+    Ok(())
+}
+
+/// Explains the since of issued cell args or a raw since value. Issued cells are committed in
+/// the genesis block, so a relative since counts from the genesis.
+pub fn since(input: &str, outset: &Outset, target: u64) -> Result<(), Error> {
+    let raw = Since::parse_raw(input)?;
+    let since = Since::from_raw(raw)?;
+    println!("since: 0x{:016x}", raw);
+    println!("decoded: {}", since);
+
+    match since.metric {
+        SinceMetric::Epoch(epoch) => {
+            if since.relative {
+                println!(
+                    "unlock after: {} + {}/{} epochs since the cell is committed",
+                    epoch.number(),
+                    epoch.index(),
+                    epoch.length()
+                );
+            } else {
+                println!(
+                    "unlock epoch: {} + {}/{} on mainnet",
+                    epoch.number(),
+                    epoch.index(),
+                    epoch.length()
+                );
+            }
+            println!(
+                "estimated date: {} (target epoch {})",
+                format_time(&outset.epoch_date(epoch, target)),
+                target
+            );
+            // the shape `Outset::since_epoch` gives to lock dates before the launch
+            if !since.relative
+                && epoch.number() == 0
+                && epoch.index() == 0
+                && epoch.length() == outset.epoch_length
+            {
+                println!(
+                    "clamped: the lock date is not after the genesis, the lock is set to zero"
+                );
+            }
+        }
+        SinceMetric::BlockNumber(number) => {
+            if since.relative {
+                println!(
+                    "unlock after: {} blocks since the cell is committed",
+                    number
+                );
+            } else {
+                println!("unlock block: {} on mainnet", number);
+            }
+        }
+        SinceMetric::Timestamp(timestamp) => {
+            if since.relative {
+                println!(
+                    "unlock after: {} seconds since the cell is committed",
+                    timestamp
+                );
+                let genesis = outset.epoch_date(EpochNumberWithFraction::new(0, 0, 1), target);
+                let date = Duration::from_std(StdDuration::from_secs(timestamp))
+                    .ok()
+                    .and_then(|duration| genesis.checked_add_signed(duration));
+                match date {
+                    Some(date) => {
+                        println!(
+                            "estimated date: {} (target epoch {})",
+                            format_time(&date),
+                            target
+                        )
+                    }
+                    None => println!("estimated date: out of range"),
+                }
+            } else {
+                match Utc.timestamp_opt(timestamp as i64, 0).single() {
+                    Some(time) => println!("unlock time: {}", format_time(&time)),
+                    None => println!("unlock time: out of range"),
+                }
+            }
+        }
+    }
+    Ok(())
+}
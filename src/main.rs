@@ -1,25 +1,43 @@
 mod address;
 mod date;
 mod explorer;
+mod export;
+mod fetcher;
 mod input;
+mod inspect;
+mod manifest;
+mod multisig;
 mod rpc;
+mod signature;
+mod since;
 mod template;
+mod vesting;
 
-use crate::address::Address;
+use crate::address::{short_lock, Address, CodeHashIndex, NetworkType};
+use chrono::{DateTime, Duration, Utc};
 use ckb_chain_spec::ChainSpec;
-use ckb_types::{
-    bytes::Bytes,
-    core::{capacity_bytes, Capacity},
-};
+use ckb_types::{bytes::Bytes, core::Capacity, packed::Script, prelude::*};
 use clap::{load_yaml, value_t, App};
+use date::{format_time, Calibration, Outset};
 use explorer::Explorer;
-use input::{collect_allocate, parse_mining_competition_record, serialize_multisig_lock_args};
+use export::{lock_hash_records, write_lock_hashes, LockHashRecord};
+use input::{
+    check_duplicates, collect_allocate, parse_mining_competition_record,
+    serialize_multisig_lock_args, AllocateContext, DuplicatePolicy, InputFormat, RowError,
+};
+use manifest::{
+    InputDir, InputFile, Inputs, Issued, Manifest, OutputManifest, PartitionKind, KEYRING_FILE,
+};
 use sha2::{Digest, Sha256};
-use std::collections::BTreeMap;
+use signature::Keyring;
+use since::{Since, SinceMetric};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fs;
 use std::io::BufReader;
+use std::iter;
+use std::path::PathBuf;
 use std::process::exit;
-use template::{IssuedCell, Spec};
+use template::{dedup_labels, IssuedCell, Spec};
 use tinytemplate::TinyTemplate;
 
 static TEMPLATE: &str = include_str!("spec.toml.tt");
@@ -28,12 +46,19 @@ const DEFAULT_CODE_HASH: &str =
 const MULTISIG_CODE_HASH: &str =
     "0x5c5069eb0857efc65e1bca0c07df34c31663b3622fd3876c876320fc9634e2a8";
 const DEFAULT_TARGET_EPOCH: u64 = 89;
-const MINING_COMPETITION_REWARD: Capacity = capacity_bytes!(168_000_000); // 0.5%
-const FOUNDATION_RESERVE: Capacity = capacity_bytes!(672_000_000); // 2%
-const INCENTIVES_ADDRESS: &str = "ckb1qyqy6mtud5sgctjwgg6gydd0ea05mr339lnslczzrc";
-const FOUNDATION_ADDRESS: &str = "ckb1qyqyz340d4nhgtx2s75mp5wnavrsu7j5fcwqktprrp";
-const FOUNDATION_LOCK: &str = "2020-07-01";
-const INITIAL_ISSUES: Capacity = capacity_bytes!(33_600_000_000);
+const DEFAULT_LOCK_HASHES_FILE: &str = "lina.lock_hashes.csv";
+const DEFAULT_BATCH_SIZE: u64 = 100;
+const DEFAULT_JOBS: usize = 4;
+
+/// Capacities issued by the template itself.
+struct TemplateCells {
+    burn: Capacity,
+    /// Genesis message cell, system cells and dep groups.
+    occupied: Capacity,
+    /// Code hashes a lock script can refer to in genesis, the data hashes of the cells and the
+    /// script hashes of their types, such as the type IDs of the system cells, with the hash type.
+    code_hashes: BTreeSet<(String, String)>,
+}
 
 fn main() {
     let yaml = load_yaml!("cli.yml");
@@ -48,25 +73,185 @@ fn main() {
         exit(1);
     }
 
+    let input_dir = match matches.value_of("input-dir") {
+        Some(dir) => InputDir::Path(PathBuf::from(dir)),
+        None => InputDir::Embedded,
+    };
+
+    let (manifest, manifest_file) = load_manifest(&input_dir);
+    let outset = manifest.outset().unwrap();
+
+    if let Some(matches) = matches.subcommand_matches("address") {
+        inspect::address(
+            matches.value_of("address").unwrap(),
+            matches.value_of("lock"),
+            &outset,
+            target,
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("address error: {}", e);
+            exit(1);
+        });
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("since") {
+        inspect::since(matches.value_of("since").unwrap(), &outset, target).unwrap_or_else(|e| {
+            eprintln!("since error: {}", e);
+            exit(1);
+        });
+        return;
+    }
+
+    let lenient = matches.is_present("lenient");
+    let duplicates = matches
+        .value_of("duplicates")
+        .map(|policy| {
+            policy.parse::<DuplicatePolicy>().unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                exit(1);
+            })
+        })
+        .unwrap_or(DuplicatePolicy::Keep);
+
+    let input_format = matches.value_of("input-format").map(|format| {
+        format.parse::<InputFormat>().unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            exit(1);
+        })
+    });
+
+    let lock_hashes_file = matches
+        .value_of("lock-hashes")
+        .unwrap_or(DEFAULT_LOCK_HASHES_FILE);
+
+    let batch_size = value_t!(matches, "batch-size", u64).unwrap_or(DEFAULT_BATCH_SIZE);
+    if batch_size == 0 {
+        eprintln!("batch size must be positive");
+        exit(1);
+    }
+    let jobs = value_t!(matches, "jobs", usize).unwrap_or(DEFAULT_JOBS);
+    if jobs == 0 {
+        eprintln!("jobs must be positive");
+        exit(1);
+    }
+
+    let calibration = matches.value_of("calibrate").map(|calibration| {
+        calibration.parse::<Calibration>().unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            exit(1);
+        })
+    });
+
     let verbose = matches.is_present("verbose");
     if verbose {
         println!("url = {}", url);
         println!("target = {}", target);
+        println!("input = {}", input_dir);
     }
 
-    let foundation_reserve = foundation_reserve(target);
-    let allocate = reduce_allocate(target);
+    let keyring = load_keyring(&input_dir, matches.value_of("keyring"));
+    let mut inputs = Inputs::new(input_dir, keyring, &manifest_file).unwrap_or_else(|e| {
+        eprintln!("input error: {}", e);
+        exit(1);
+    });
+
+    let template_cells = template_cells();
+    let mut foundation_reserve =
+        issue_foundation_reserve(&manifest, &template_cells, &outset, target);
+    let mut errors = vec![];
+    let allocate_files = read_allocate(&mut inputs, &manifest);
+    let mut allocate = reduce_allocate(
+        &allocate_files,
+        &manifest,
+        input_format,
+        &outset,
+        target,
+        &mut errors,
+    );
+    for duplicate in check_duplicates(&mut allocate, duplicates, &mut errors) {
+        println!("Duplicate lock ({}): {}", duplicates, duplicate);
+    }
+    check_code_hashes(&template_cells, &allocate);
+    let allocate_errors = errors.len();
 
     let mut records = BTreeMap::new();
-    load_mining_competition_records(&mut records);
-    let explorer = Explorer::new(url, target);
+    load_mining_competition_records(
+        &mut inputs,
+        &manifest,
+        input_format,
+        &mut records,
+        &mut errors,
+    );
+    check_errors(&errors, lenient);
+    let explorer = Explorer::new(url, target, batch_size, jobs);
     let (timestamp, compact_target, message, epoch_length) =
         explorer.collect(&mut records).unwrap_or_else(|e| {
             eprintln!("explorer error: {}", e);
             exit(1);
         });
-    let testnet_incentives = reduce_mining_competition_records(records);
+    let testnet_incentives = reduce_mining_competition_records(&manifest, records);
+
+    let mut epoch_duration = None;
+    if let Some(calibration) = calibration {
+        let calibrated = Outset {
+            epoch_duration: explorer
+                .calibrate(calibration, compact_target)
+                .unwrap_or_else(|e| {
+                    eprintln!("explorer error: {}", e);
+                    exit(1);
+                }),
+            ..outset
+        };
+        foundation_reserve =
+            issue_foundation_reserve(&manifest, &template_cells, &calibrated, target);
+        let mut calibrated_errors = vec![];
+        allocate = reduce_allocate(
+            &allocate_files,
+            &manifest,
+            input_format,
+            &calibrated,
+            target,
+            &mut calibrated_errors,
+        );
+        check_duplicates(&mut allocate, duplicates, &mut calibrated_errors);
+        // The rows were checked with the nominal duration, but absolute timestamps must be after
+        // the estimated genesis, which moves with the duration.
+        let nominal_errors: HashSet<_> = errors[..allocate_errors]
+            .iter()
+            .map(|error| (error.file.as_str(), error.line))
+            .collect();
+        let calibrated_errors: Vec<_> = calibrated_errors
+            .into_iter()
+            .filter(|error| !nominal_errors.contains(&(error.file.as_str(), error.line)))
+            .collect();
+        check_errors(&calibrated_errors, lenient);
+        report_drift(&allocate, &foundation_reserve, &outset, &calibrated, target);
+        epoch_duration = Some(calibrated.epoch_duration);
+    }
 
+    check_partitions(
+        &manifest,
+        &Issued {
+            burn: template_cells.burn,
+            occupied: template_cells.occupied,
+            allocate: &allocate,
+            foundation_reserve: &foundation_reserve,
+            testnet_incentives: &testnet_incentives,
+        },
+    );
+    let lock_hashes = lock_hash_records(
+        &manifest,
+        &allocate,
+        &foundation_reserve,
+        &testnet_incentives,
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("lock hash error: {}", e);
+        exit(1);
+    });
+
+    let mut allocate: Vec<_> = allocate.into_iter().flat_map(|(_, cells)| cells).collect();
+    dedup_labels(&mut allocate);
     let context = Spec {
         timestamp,
         compact_target: format!("0x{:x}", compact_target),
@@ -92,14 +277,30 @@ fn main() {
         println!("hash = {:#x}", consensus.genesis_block().hash());
     }
     assert_eq!(
-        issued, INITIAL_ISSUES,
-        "initial issued must be 33_600_000_000"
+        issued,
+        manifest.total().unwrap(),
+        "initial issued must be {}",
+        manifest.total
     );
 
-    write_file(rendered);
+    let output_manifest = OutputManifest {
+        version: env!("CARGO_PKG_VERSION"),
+        target,
+        input_dir: inputs.dir.to_string(),
+        lenient,
+        duplicates,
+        epoch_duration,
+        inputs: &inputs.loaded,
+    };
+    write_file(rendered, &output_manifest, lock_hashes_file, &lock_hashes);
 }
 
-fn write_file(spec: String) {
+fn write_file(
+    spec: String,
+    output_manifest: &OutputManifest,
+    lock_hashes_file: &str,
+    lock_hashes: &[LockHashRecord],
+) {
     fs::write("lina.toml", &spec).unwrap();
     println!("Created spec: lina.toml");
 
@@ -116,68 +317,159 @@ fn write_file(spec: String) {
     println!("Created checksum: lina.toml.sha256sum");
     println!("sha256sum of lina.toml: {:#x}", sha256sum);
 
+    fs::write(
+        "lina.manifest.json",
+        serde_json::to_string_pretty(output_manifest).unwrap(),
+    )
+    .unwrap();
+    println!("Created manifest: lina.manifest.json");
+
+    write_lock_hashes(lock_hashes_file, lock_hashes).unwrap();
+    println!("Created lock hashes: {}", lock_hashes_file);
+
     println!("\nPlease use the latest ckb release to import the spec and start the node:");
     println!("     ckb init --import-spec lina.toml --chain mainnet");
     println!("     ckb run");
 }
 
-fn reduce_allocate(target: u64) -> Vec<IssuedCell> {
-    let allocate = include_bytes!("input/genesis_final.csv");
-    let reader = BufReader::new(&allocate[..]);
-    collect_allocate(reader, target)
+fn read_input(inputs: &mut Inputs, name: &str) -> InputFile {
+    let (file, signer) = inputs.read(name).unwrap_or_else(|e| {
+        eprintln!("input error: {}", e);
+        exit(1);
+    });
+    println!(
+        "Loaded {} (sha256: {}, signed by {})",
+        file.name, file.sha256, signer
+    );
+    file
 }
 
-fn load_mining_competition_records(map: &mut BTreeMap<Bytes, Capacity>) {
-    {
-        let round1 = include_bytes!("input/round1.csv");
-        let reader = BufReader::new(&round1[..]);
-        parse_mining_competition_record(reader, map);
-    }
+fn load_keyring(input_dir: &InputDir, path: Option<&str>) -> Keyring {
+    let content = match path {
+        Some(path) => fs::read(path).map_err(failure::Error::from),
+        None => input_dir.read(KEYRING_FILE).map(|file| file.content),
+    };
+    content
+        .and_then(|content| Keyring::from_armor(&content))
+        .unwrap_or_else(|e| {
+            eprintln!("keyring error: {}", e);
+            eprintln!(
+                "export the trusted keys listed in src/input/README.md and pass them with --keyring"
+            );
+            exit(1);
+        })
+}
 
-    {
-        let round2_epoch = include_bytes!("input/round2.epoch.csv");
-        let reader = BufReader::new(&round2_epoch[..]);
-        parse_mining_competition_record(reader, map);
-    }
+fn load_manifest(input_dir: &InputDir) -> (Manifest, InputFile) {
+    let (manifest, file) = input_dir.manifest().unwrap_or_else(|e| {
+        eprintln!("input error: {}", e);
+        exit(1);
+    });
+    println!("Loaded {} (sha256: {})", file.name, file.sha256);
+    manifest.validate().unwrap_or_else(|e| {
+        eprintln!("manifest error: {}", e);
+        exit(1);
+    });
+    (manifest, file)
+}
 
-    {
-        let round2_mininng = include_bytes!("input/round2.mining.csv");
-        let reader = BufReader::new(&round2_mininng[..]);
-        parse_mining_competition_record(reader, map);
-    }
+fn check_partitions(manifest: &Manifest, issued: &Issued) {
+    let checks = manifest.check(issued).unwrap_or_else(|e| {
+        eprintln!("manifest error: {}", e);
+        exit(1);
+    });
 
-    {
-        let round3_epoch = include_bytes!("input/round3.epoch.csv");
-        let reader = BufReader::new(&round3_epoch[..]);
-        parse_mining_competition_record(reader, map);
+    println!("Partitions:");
+    for check in &checks {
+        let status = if check.is_ok() { "ok" } else { "MISMATCH" };
+        println!("  {} {}", check, status);
     }
-
-    {
-        let round3_mininng = include_bytes!("input/round3.mining.csv");
-        let reader = BufReader::new(&round3_mininng[..]);
-        parse_mining_competition_record(reader, map);
+    if checks.iter().any(|check| !check.is_ok()) {
+        eprintln!("partitions do not match manifest.toml");
+        exit(1);
     }
+}
 
-    {
-        let round4 = include_bytes!("input/round4.csv");
-        let reader = BufReader::new(&round4[..]);
-        parse_mining_competition_record(reader, map);
+fn check_errors(errors: &[RowError], lenient: bool) {
+    if errors.is_empty() {
+        return;
     }
 
-    {
-        let round5_stage1 = include_bytes!("input/round5.stage1.csv");
-        let reader = BufReader::new(&round5_stage1[..]);
-        parse_mining_competition_record(reader, map);
+    let level = if lenient { "warning" } else { "error" };
+    for error in errors {
+        eprintln!("{}: {}", level, error);
+    }
+    if lenient {
+        eprintln!("skipped {} invalid rows", errors.len());
+    } else {
+        eprintln!(
+            "found {} invalid rows, pass --lenient to skip them",
+            errors.len()
+        );
+        exit(1);
     }
+}
+
+fn read_allocate(inputs: &mut Inputs, manifest: &Manifest) -> Vec<InputFile> {
+    manifest
+        .allocate
+        .iter()
+        .map(|source| read_input(inputs, &source.file))
+        .collect()
+}
 
-    {
-        let round5_stage2 = include_bytes!("input/round5.stage2.csv");
-        let reader = BufReader::new(&round5_stage2[..]);
-        parse_mining_competition_record(reader, map);
+fn reduce_allocate(
+    files: &[InputFile],
+    manifest: &Manifest,
+    input_format: Option<InputFormat>,
+    outset: &Outset,
+    target: u64,
+    errors: &mut Vec<RowError>,
+) -> Vec<(String, Vec<IssuedCell>)> {
+    manifest
+        .allocate
+        .iter()
+        .zip(files)
+        .map(|(source, file)| {
+            let reader = BufReader::new(&file.content[..]);
+            let context = AllocateContext {
+                format: input_format.unwrap_or_else(|| InputFormat::from_file(&file.name)),
+                network: source.network,
+                categories: manifest.categories(&file.name),
+                schedules: &manifest.schedules,
+                outset: *outset,
+                target,
+            };
+            let cells = collect_allocate(reader, &file.name, &context, errors);
+            (file.name.clone(), cells)
+        })
+        .collect()
+}
+
+fn load_mining_competition_records(
+    inputs: &mut Inputs,
+    manifest: &Manifest,
+    input_format: Option<InputFormat>,
+    map: &mut BTreeMap<(CodeHashIndex, Bytes), Capacity>,
+    errors: &mut Vec<RowError>,
+) {
+    for source in &manifest.competition {
+        let file = read_input(inputs, &source.file);
+        let reader = BufReader::new(&file.content[..]);
+        let format = input_format.unwrap_or_else(|| InputFormat::from_file(&file.name));
+        parse_mining_competition_record(
+            reader,
+            &file.name,
+            format,
+            source.network,
+            &source.invalid_locks,
+            map,
+            errors,
+        );
     }
 }
 
-fn foundation_reserve(target: u64) -> IssuedCell {
+fn template_cells() -> TemplateCells {
     let dummy = Spec {
         timestamp: 0,
         compact_target: "0x20ffffff".to_string(),
@@ -193,6 +485,14 @@ fn foundation_reserve(target: u64) -> IssuedCell {
     let rendered = tt.render("dummy", &dummy).unwrap();
 
     let mut spec: ChainSpec = toml::from_str(&rendered).unwrap();
+    // only the burned cell is left in the dummy spec
+    let burn = spec
+        .genesis
+        .issued_cells
+        .iter()
+        .map(|cell| cell.capacity)
+        .try_fold(Capacity::zero(), Capacity::safe_add)
+        .unwrap();
     // clean issued_cells
     spec.genesis.issued_cells = vec![];
 
@@ -202,18 +502,152 @@ fn foundation_reserve(target: u64) -> IssuedCell {
         .outputs_capacity()
         .unwrap();
 
-    let foundation_reserve = FOUNDATION_RESERVE.safe_sub(occupied).unwrap();
+    let mut code_hashes = BTreeSet::new();
+    for tx in consensus.genesis_block().transactions() {
+        for (output, data) in tx.outputs_with_data_iter() {
+            if !data.is_empty() {
+                let data_hash = ckb_hash::blake2b_256(&data);
+                code_hashes.insert((
+                    format!("0x{}", faster_hex::hex_string(&data_hash).unwrap()),
+                    "data".to_string(),
+                ));
+            }
+            if let Some(script) = output.type_().to_opt() {
+                let type_hash = script.calc_script_hash();
+                code_hashes.insert((
+                    format!(
+                        "0x{}",
+                        faster_hex::hex_string(type_hash.as_slice()).unwrap()
+                    ),
+                    "type".to_string(),
+                ));
+            }
+        }
+    }
+
+    TemplateCells {
+        burn,
+        occupied,
+        code_hashes,
+    }
+}
+
+/// Warns about the allocation cells whose lock refers to no cell in genesis, they cannot be
+/// unlocked until the code is deployed.
+fn check_code_hashes(template_cells: &TemplateCells, allocate: &[(String, Vec<IssuedCell>)]) {
+    for (file, cells) in allocate {
+        for cell in cells {
+            let key = (cell.code_hash.clone(), cell.hash_type.clone());
+            if !template_cells.code_hashes.contains(&key) {
+                println!(
+                    "Warning: {}:{}: code hash {} with hash type {} points at nothing in genesis",
+                    file,
+                    cell.line.unwrap_or(0),
+                    cell.code_hash,
+                    cell.hash_type
+                );
+            }
+        }
+    }
+}
+
+/// Reports how far each lock given as a time unlocks from it on a chain whose epochs last as
+/// `calibrated`, when converted with the nominal and the calibrated epoch duration.
+fn report_drift(
+    allocate: &[(String, Vec<IssuedCell>)],
+    foundation_reserve: &IssuedCell,
+    nominal: &Outset,
+    calibrated: &Outset,
+    target: u64,
+) {
+    println!(
+        "Calibrated epoch duration: {} seconds (nominal {} seconds)",
+        calibrated.epoch_duration, nominal.epoch_duration
+    );
+    let cells = allocate
+        .iter()
+        .flat_map(|(file, cells)| {
+            cells
+                .iter()
+                .map(move |cell| (format!("{}:{}", file, cell.line.unwrap_or(0)), cell))
+        })
+        .chain(iter::once((
+            "foundation reserve".to_string(),
+            foundation_reserve,
+        )));
+    for (source, cell) in cells {
+        // Timestamp and relative locks do not depend on the epoch duration.
+        let unlock_time = match cell.unlock_time {
+            Some(ref unlock_time) => unlock_time,
+            None => continue,
+        };
+        match Since::parse_raw(&cell.args).and_then(Since::from_raw) {
+            Ok(Since {
+                relative: false,
+                metric: SinceMetric::Epoch(_),
+            }) => {}
+            _ => continue,
+        }
+        let date = DateTime::parse_from_rfc3339(unlock_time)
+            .unwrap()
+            .with_timezone(&Utc);
+        println!(
+            "  {} unlocks at {}: nominal drift {}, calibrated drift {}",
+            source,
+            unlock_time,
+            format_drift(nominal.drift(calibrated, &date, target)),
+            format_drift(calibrated.drift(calibrated, &date, target))
+        );
+    }
+}
+
+fn format_drift(drift: Duration) -> String {
+    format!("{:+.1} hours", drift.num_seconds() as f64 / 3600.0)
+}
+
+fn issue_foundation_reserve(
+    manifest: &Manifest,
+    template_cells: &TemplateCells,
+    outset: &Outset,
+    target: u64,
+) -> IssuedCell {
+    let partition = manifest.partition(PartitionKind::Foundation).unwrap();
+    let foundation_reserve = manifest
+        .expected(partition)
+        .unwrap()
+        .safe_sub(template_cells.occupied)
+        .unwrap();
 
-    let args = serialize_multisig_lock_args(FOUNDATION_ADDRESS, FOUNDATION_LOCK, target).unwrap();
+    let multisig = partition.multisig().unwrap_or_else(|e| {
+        eprintln!("foundation reserve error: {}", e);
+        exit(1);
+    });
+    let (args, unlock_time) =
+        serialize_multisig_lock_args(&multisig, partition.lock.as_ref().unwrap(), outset, target)
+            .unwrap_or_else(|e| {
+                eprintln!("foundation reserve error: {}", e);
+                exit(1);
+            });
 
     IssuedCell {
-        capacity: foundation_reserve.as_u64(),
-        code_hash: MULTISIG_CODE_HASH.to_string(),
-        args: format!("0x{}", faster_hex::hex_string(&args[..]).unwrap()),
+        address: Some(match partition.address {
+            Some(ref address) => address.clone(),
+            None => partition.signers.join(";"),
+        }),
+        lock_input: partition.lock.clone(),
+        unlock_time: unlock_time.as_ref().map(format_time),
+        ..IssuedCell::new(
+            foundation_reserve.as_u64(),
+            short_lock(CodeHashIndex::Multisig, &args),
+        )
     }
 }
 
-fn reduce_mining_competition_records(map: BTreeMap<Bytes, Capacity>) -> Vec<IssuedCell> {
+fn reduce_mining_competition_records(
+    manifest: &Manifest,
+    map: BTreeMap<(CodeHashIndex, Bytes), Capacity>,
+) -> Vec<IssuedCell> {
+    let partition = manifest.partition(PartitionKind::Incentives).unwrap();
     let total = map
         .iter()
         .map(|(_, capacity)| *capacity)
@@ -224,27 +658,35 @@ fn reduce_mining_competition_records(map: BTreeMap<Bytes, Capacity>) -> Vec<Issu
 
     let mut issued: Vec<_> = map
         .into_iter()
-        .map(|(args, capacity)| IssuedCell {
-            capacity: capacity.as_u64(),
-            code_hash: DEFAULT_CODE_HASH.to_string(),
-            args: format!("0x{}", faster_hex::hex_string(&args[..]).unwrap()),
+        .map(|((index, args), capacity)| {
+            let lock = short_lock(index, &args);
+            // the testnet address of the miner, as in the competition files
+            let address = Address::from_script(&lock, NetworkType::Testnet).unwrap();
+            IssuedCell {
+                address: Some(address.to_string()),
+                ..IssuedCell::new(capacity.as_u64(), lock)
+            }
         })
         .collect();
 
-    let remain = MINING_COMPETITION_REWARD
+    let remain = manifest
+        .expected(partition)
+        .unwrap()
         .safe_sub(total)
         .unwrap_or_else(|_| {
             exit(1);
         });
 
-    let incentives_address = Address::from_str(INCENTIVES_ADDRESS).unwrap();
+    let incentives_address =
+        Address::from_str_on(partition.address().unwrap(), NetworkType::Mainnet).unwrap_or_else(
+            |e| {
+                eprintln!("testnet incentives error: {}", e);
+                exit(1);
+            },
+        );
     issued.push(IssuedCell {
-        capacity: remain.as_u64(),
-        code_hash: DEFAULT_CODE_HASH.to_string(),
-        args: format!(
-            "0x{}",
-            faster_hex::hex_string(&incentives_address.args[..]).unwrap()
-        ),
+        address: partition.address.clone(),
+        ..IssuedCell::new(remain.as_u64(), Script::from(&incentives_address))
     });
 
     issued
@@ -0,0 +1,896 @@
+use crate::{
+    address::NetworkType, date::Outset, input::DuplicatePolicy, multisig::MultisigConfig,
+    signature::Keyring, template::IssuedCell, DEFAULT_CODE_HASH, MULTISIG_CODE_HASH,
+};
+use chrono::{DateTime, Utc};
+use ckb_types::core::Capacity;
+use failure::{Error, Fail};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+pub const MANIFEST_FILE: &str = "manifest.toml";
+pub const KEYRING_FILE: &str = "keyring.asc";
+const BYTE_SHANNONS: u64 = 100_000_000;
+const FULL_SHARE: u64 = 10_000;
+
+static EMBEDDED: &[(&str, &[u8])] = &[
+    ("manifest.toml", include_bytes!("input/manifest.toml")),
+    ("keyring.asc", include_bytes!("input/keyring.asc")),
+    (
+        "genesis_final.csv",
+        include_bytes!("input/genesis_final.csv"),
+    ),
+    ("round1.csv", include_bytes!("input/round1.csv")),
+    ("round2.epoch.csv", include_bytes!("input/round2.epoch.csv")),
+    (
+        "round2.mining.csv",
+        include_bytes!("input/round2.mining.csv"),
+    ),
+    ("round3.epoch.csv", include_bytes!("input/round3.epoch.csv")),
+    (
+        "round3.mining.csv",
+        include_bytes!("input/round3.mining.csv"),
+    ),
+    ("round4.csv", include_bytes!("input/round4.csv")),
+    (
+        "round5.stage1.csv",
+        include_bytes!("input/round5.stage1.csv"),
+    ),
+    (
+        "round5.stage2.csv",
+        include_bytes!("input/round5.stage2.csv"),
+    ),
+    (
+        "genesis_final.csv.asc",
+        include_bytes!("input/genesis_final.csv.asc"),
+    ),
+    ("round1.csv.asc", include_bytes!("input/round1.csv.asc")),
+    (
+        "round2.epoch.csv.asc",
+        include_bytes!("input/round2.epoch.csv.asc"),
+    ),
+    (
+        "round2.mining.csv.asc",
+        include_bytes!("input/round2.mining.csv.asc"),
+    ),
+    (
+        "round3.epoch.csv.asc",
+        include_bytes!("input/round3.epoch.csv.asc"),
+    ),
+    (
+        "round3.mining.csv.asc",
+        include_bytes!("input/round3.mining.csv.asc"),
+    ),
+    ("round4.csv.asc", include_bytes!("input/round4.csv.asc")),
+    (
+        "round5.stage1.csv.asc",
+        include_bytes!("input/round5.stage1.csv.asc"),
+    ),
+    (
+        "round5.stage2.csv.asc",
+        include_bytes!("input/round5.stage2.csv.asc"),
+    ),
+];
+
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    /// Initial issuance in CKBytes.
+    pub total: u64,
+    #[serde(default)]
+    pub allocate: Vec<Source>,
+    #[serde(default)]
+    pub competition: Vec<Source>,
+    pub partitions: Vec<Partition>,
+    /// Vesting schedules referenced as `schedule:<name>` in the lock column.
+    #[serde(default)]
+    pub schedules: BTreeMap<String, Schedule>,
+    /// Overrides how lock dates are converted to epochs, see `Outset`.
+    #[serde(default)]
+    pub outset: OutsetConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct OutsetConfig {
+    /// RFC 3339 time, such as "2019-11-16T06:00:00Z".
+    pub anchor: Option<String>,
+    pub base_epoch: Option<u64>,
+    /// In seconds.
+    pub epoch_duration: Option<u64>,
+    pub epoch_length: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Source {
+    pub file: String,
+    /// Network of the addresses in the file, default to mainnet. Testnet addresses are only
+    /// accepted where declared.
+    #[serde(default)]
+    pub network: NetworkType,
+    /// Addresses in a competition round which are known to be invalid locks.
+    #[serde(default)]
+    pub invalid_locks: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionKind {
+    /// The burned cell in the template.
+    Burn,
+    /// Cells generated from the `allocate` files.
+    Allocate,
+    Foundation,
+    /// Cells generated from the `competition` files and the explorer.
+    Incentives,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LockKind {
+    Burn,
+    Sighash,
+    Multisig,
+    /// Any other lock script, given by a full payload address.
+    Custom,
+}
+
+impl LockKind {
+    pub fn from_script(code_hash: &str, hash_type: &str) -> LockKind {
+        match (code_hash, hash_type) {
+            (DEFAULT_CODE_HASH, "type") => LockKind::Sighash,
+            (MULTISIG_CODE_HASH, "type") => LockKind::Multisig,
+            _ => LockKind::Custom,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Partition {
+    /// Also used in the category column of the `allocate` files.
+    pub name: String,
+    /// Rendered as a comment before the issued cells of this partition.
+    pub label: Option<String>,
+    pub kind: PartitionKind,
+    /// Percentage of the total issuance, such as "21.5%".
+    pub share: String,
+    /// Files from `allocate` or `competition` issued in this partition.
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// Locks the cells in this partition may use.
+    pub locks: Vec<LockKind>,
+    /// The lock owner of the foundation reserve and the remaining testnet incentives.
+    pub address: Option<String>,
+    /// Signers of the foundation reserve instead of `address`.
+    #[serde(default)]
+    pub signers: Vec<String>,
+    /// M of the foundation reserve multisig lock, required with `signers`.
+    pub threshold: Option<u8>,
+    /// Number of the first signers which must sign, default to 0.
+    pub require_first_n: Option<u8>,
+    /// Lock of the foundation reserve, a date, a time or an `epoch:`, `timestamp:` or `block:`
+    /// since, which may be prefixed with `relative:`.
+    pub lock: Option<String>,
+}
+
+impl Partition {
+    /// Parses the share in basis points.
+    pub fn share(&self) -> Result<u64, Error> {
+        parse_share(&self.share).ok_or_else(|| {
+            InputError(format!("invalid share of {}: {}", self.name, self.share)).into()
+        })
+    }
+
+    pub fn address(&self) -> Result<&str, Error> {
+        self.address
+            .as_ref()
+            .map(String::as_str)
+            .ok_or_else(|| InputError(format!("partition {} requires address", self.name)).into())
+    }
+
+    /// The multisig lock of `signers`, or of `address` as the only signer.
+    pub fn multisig(&self) -> Result<MultisigConfig, Error> {
+        if self.signers.is_empty() {
+            return MultisigConfig::from_addresses(&[self.address()?], NetworkType::Mainnet, 1, 0);
+        }
+        if self.address.is_some() {
+            return Err(InputError(format!(
+                "partition {} requires either address or signers",
+                self.name
+            ))
+            .into());
+        }
+        let threshold = self
+            .threshold
+            .ok_or_else(|| InputError(format!("partition {} requires threshold", self.name)))?;
+        MultisigConfig::from_addresses(
+            &self.signers,
+            NetworkType::Mainnet,
+            threshold,
+            self.require_first_n.unwrap_or(0),
+        )
+    }
+}
+
+/// Parses a percentage such as "21.5%" in basis points.
+pub fn parse_share(input: &str) -> Option<u64> {
+    if !input.ends_with('%') {
+        return None;
+    }
+    let percent = &input[..input.len() - 1];
+    let mut parts = percent.splitn(2, '.');
+    let integer = parts.next()?.parse::<u64>().ok()?;
+    let fraction = match parts.next() {
+        Some(fraction) if fraction.len() == 1 => fraction.parse::<u64>().ok()? * 10,
+        Some(fraction) if fraction.len() == 2 => fraction.parse::<u64>().ok()?,
+        Some(_) => return None,
+        None => 0,
+    };
+    integer.checked_mul(100)?.checked_add(fraction)
+}
+
+/// The issued cells of every partition.
+pub struct Issued<'a> {
+    pub burn: Capacity,
+    /// Capacity used by the genesis message cell, system cells and dep groups.
+    pub occupied: Capacity,
+    /// Issued cells generated from each `allocate` file.
+    pub allocate: &'a [(String, Vec<IssuedCell>)],
+    pub foundation_reserve: &'a IssuedCell,
+    pub testnet_incentives: &'a [IssuedCell],
+}
+
+/// The result of checking one partition, or several partitions sharing the same sources.
+pub struct PartitionCheck {
+    pub name: String,
+    /// Share in basis points.
+    pub share: u64,
+    pub expected: Capacity,
+    pub actual: Capacity,
+    /// Cells issued with a lock not allowed by the partition.
+    pub violations: Vec<String>,
+}
+
+impl PartitionCheck {
+    pub fn is_ok(&self) -> bool {
+        self.expected == self.actual && self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for PartitionCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<40} {:>6}.{:02}% expected {:>28} actual {:>28}",
+            self.name,
+            self.share / 100,
+            self.share % 100,
+            format_ckb(self.expected),
+            format_ckb(self.actual)
+        )?;
+        for violation in &self.violations {
+            write!(f, "\n    {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn format_ckb(capacity: Capacity) -> String {
+    let shannons = capacity.as_u64();
+    format!(
+        "{}.{:08} CKB",
+        shannons / BYTE_SHANNONS,
+        shannons % BYTE_SHANNONS
+    )
+}
+
+impl Manifest {
+    pub fn total(&self) -> Result<Capacity, Error> {
+        self.total
+            .checked_mul(BYTE_SHANNONS)
+            .map(Capacity::shannons)
+            .ok_or_else(|| InputError(format!("total overflow: {}", self.total)).into())
+    }
+
+    pub fn partition(&self, kind: PartitionKind) -> Result<&Partition, Error> {
+        let mut partitions = self.partitions.iter().filter(|p| p.kind == kind);
+        match (partitions.next(), partitions.next()) {
+            (Some(partition), None) => Ok(partition),
+            _ => Err(InputError(format!("expect exactly one {:?} partition", kind)).into()),
+        }
+    }
+
+    /// Maps the names of the partitions reading `file` to their labels.
+    pub fn categories(&self, file: &str) -> BTreeMap<String, String> {
+        self.partitions
+            .iter()
+            .filter(|partition| {
+                partition.kind == PartitionKind::Allocate
+                    && partition.sources.iter().any(|source| source == file)
+            })
+            .map(|partition| {
+                let label = partition
+                    .label
+                    .clone()
+                    .unwrap_or_else(|| partition.name.clone());
+                (partition.name.clone(), label)
+            })
+            .collect()
+    }
+
+    /// Capacity of `partition` according to its share.
+    pub fn expected(&self, partition: &Partition) -> Result<Capacity, Error> {
+        self.expected_shares(partition.share()?)
+    }
+
+    fn expected_shares(&self, share: u64) -> Result<Capacity, Error> {
+        let total = u128::from(self.total()?.as_u64());
+        let expected = total * u128::from(share) / u128::from(FULL_SHARE);
+        Ok(Capacity::shannons(expected as u64))
+    }
+
+    /// The default `Outset` with the overrides in the manifest.
+    pub fn outset(&self) -> Result<Outset, Error> {
+        let default = Outset::default();
+        let config = &self.outset;
+        let anchor = match config.anchor {
+            Some(ref anchor) => DateTime::parse_from_rfc3339(anchor)
+                .map_err(|e| InputError(format!("invalid outset anchor {}: {}", anchor, e)))?
+                .with_timezone(&Utc),
+            None => default.anchor,
+        };
+        let outset = Outset {
+            anchor,
+            base_epoch: config.base_epoch.unwrap_or(default.base_epoch),
+            epoch_duration: config.epoch_duration.unwrap_or(default.epoch_duration),
+            epoch_length: config.epoch_length.unwrap_or(default.epoch_length),
+        };
+        if outset.epoch_duration == 0 {
+            return Err(InputError("outset epoch_duration must be positive".to_string()).into());
+        }
+        if outset.epoch_length == 0 || outset.epoch_length > 0xffff {
+            return Err(InputError(format!(
+                "outset epoch_length must be between 1 and 65535, got {}",
+                outset.epoch_length
+            ))
+            .into());
+        }
+        Ok(outset)
+    }
+
+    /// Checks that the shares add up to 100% and every source belongs to a partition.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.outset()?;
+        self.partition(PartitionKind::Burn)?;
+        self.partition(PartitionKind::Foundation)?.multisig()?;
+        self.partition(PartitionKind::Incentives)?.address()?;
+        if self.partition(PartitionKind::Foundation)?.lock.is_none() {
+            return Err(InputError("foundation partition requires lock".to_string()).into());
+        }
+
+        let mut shares = 0;
+        for partition in &self.partitions {
+            shares += partition.share()?;
+            if let Some(ref label) = partition.label {
+                if label.contains(&['\n', '\r'][..]) {
+                    return Err(InputError(format!(
+                        "label of partition {} cannot contain line breaks",
+                        partition.name
+                    ))
+                    .into());
+                }
+            }
+            let files = match partition.kind {
+                PartitionKind::Allocate => &self.allocate,
+                PartitionKind::Incentives => &self.competition,
+                _ if partition.sources.is_empty() => continue,
+                _ => {
+                    return Err(InputError(format!(
+                        "partition {} cannot have sources",
+                        partition.name
+                    ))
+                    .into())
+                }
+            };
+            for source in &partition.sources {
+                if !files.iter().any(|file| &file.file == source) {
+                    return Err(InputError(format!(
+                        "source {} of partition {} is not listed as {:?} input",
+                        source, partition.name, partition.kind
+                    ))
+                    .into());
+                }
+            }
+        }
+        if shares != FULL_SHARE {
+            return Err(InputError(format!(
+                "partition shares add up to {}.{:02}%",
+                shares / 100,
+                shares % 100
+            ))
+            .into());
+        }
+
+        for source in self.allocate.iter().chain(self.competition.iter()) {
+            if !self
+                .partitions
+                .iter()
+                .any(|partition| partition.sources.contains(&source.file))
+            {
+                return Err(InputError(format!(
+                    "input {} does not belong to any partition",
+                    source.file
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Compares the actual capacity of every partition with its share.
+    pub fn check(&self, issued: &Issued) -> Result<Vec<PartitionCheck>, Error> {
+        let mut checks = vec![];
+
+        let burn = self.partition(PartitionKind::Burn)?;
+        checks.push(PartitionCheck {
+            name: burn.name.clone(),
+            share: burn.share()?,
+            expected: self.expected(burn)?,
+            actual: issued.burn,
+            violations: vec![],
+        });
+
+        // partitions reading the same files are checked together unless every row has a category
+        let mut groups: BTreeMap<&[String], Vec<&Partition>> = BTreeMap::new();
+        for partition in &self.partitions {
+            if partition.kind == PartitionKind::Allocate {
+                groups
+                    .entry(&partition.sources[..])
+                    .or_insert_with(Vec::new)
+                    .push(partition);
+            }
+        }
+        for (sources, partitions) in groups {
+            let cells: Vec<&IssuedCell> = issued
+                .allocate
+                .iter()
+                .filter(|(file, _)| sources.contains(file))
+                .flat_map(|(_, cells)| cells.iter())
+                .collect();
+
+            if cells.iter().all(|cell| cell.category.is_some()) {
+                for partition in partitions {
+                    let cells: Vec<&IssuedCell> = cells
+                        .iter()
+                        .filter(|cell| cell.category.as_ref() == Some(&partition.name))
+                        .cloned()
+                        .collect();
+                    checks.push(PartitionCheck {
+                        name: partition.name.clone(),
+                        share: partition.share()?,
+                        expected: self.expected(partition)?,
+                        actual: sum(cells.iter().cloned())?,
+                        violations: violations(&partition.locks, cells.iter().cloned()),
+                    });
+                }
+                continue;
+            }
+
+            let share: u64 = partitions
+                .iter()
+                .map(|partition| partition.share())
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .sum();
+            let mut locks: Vec<LockKind> = vec![];
+            for partition in &partitions {
+                locks.extend(partition.locks.iter());
+            }
+            checks.push(PartitionCheck {
+                name: partitions
+                    .iter()
+                    .map(|partition| partition.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join("+"),
+                share,
+                expected: self.expected_shares(share)?,
+                actual: sum(cells.iter().cloned())?,
+                violations: violations(&locks, cells.iter().cloned()),
+            });
+        }
+
+        let foundation = self.partition(PartitionKind::Foundation)?;
+        checks.push(PartitionCheck {
+            name: foundation.name.clone(),
+            share: foundation.share()?,
+            expected: self.expected(foundation)?,
+            actual: sum(Some(issued.foundation_reserve))?.safe_add(issued.occupied)?,
+            violations: violations(&foundation.locks, Some(issued.foundation_reserve)),
+        });
+
+        let incentives = self.partition(PartitionKind::Incentives)?;
+        checks.push(PartitionCheck {
+            name: incentives.name.clone(),
+            share: incentives.share()?,
+            expected: self.expected(incentives)?,
+            actual: sum(issued.testnet_incentives)?,
+            violations: violations(&incentives.locks, issued.testnet_incentives),
+        });
+
+        Ok(checks)
+    }
+}
+
+fn sum<'a, I: IntoIterator<Item = &'a IssuedCell>>(cells: I) -> Result<Capacity, Error> {
+    cells
+        .into_iter()
+        .map(|cell| Capacity::shannons(cell.capacity))
+        .try_fold(Capacity::zero(), Capacity::safe_add)
+        .map_err(Into::into)
+}
+
+fn violations<'a, I: IntoIterator<Item = &'a IssuedCell>>(
+    locks: &[LockKind],
+    cells: I,
+) -> Vec<String> {
+    cells
+        .into_iter()
+        .filter(|cell| !locks.contains(&LockKind::from_script(&cell.code_hash, &cell.hash_type)))
+        .map(|cell| {
+            format!(
+                "lock not allowed: {} {} {}",
+                cell.code_hash, cell.hash_type, cell.args
+            )
+        })
+        .collect()
+}
+
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Fail)]
+pub struct InputError(pub String);
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Where the manifest and the files listed in it are read from.
+pub enum InputDir {
+    Embedded,
+    Path(PathBuf),
+}
+
+pub struct InputFile {
+    pub name: String,
+    pub content: Vec<u8>,
+    pub sha256: String,
+}
+
+impl InputDir {
+    pub fn read(&self, name: &str) -> Result<InputFile, Error> {
+        let content = match self {
+            InputDir::Embedded => EMBEDDED
+                .iter()
+                .find(|(file, _)| *file == name)
+                .map(|(_, content)| content.to_vec())
+                .ok_or_else(|| InputError(format!("{} is not embedded", name)))?,
+            InputDir::Path(dir) => fs::read(dir.join(name))
+                .map_err(|e| InputError(format!("failed to read {}: {}", name, e)))?,
+        };
+
+        let sha256 = {
+            let mut hasher = Sha256::new();
+            hasher.input(&content);
+            format!("{:x}", hasher.result())
+        };
+
+        Ok(InputFile {
+            name: name.to_string(),
+            content,
+            sha256,
+        })
+    }
+
+    pub fn manifest(&self) -> Result<(Manifest, InputFile), Error> {
+        let file = self.read(MANIFEST_FILE)?;
+        let manifest = toml::from_slice(&file.content)
+            .map_err(|e| InputError(format!("invalid {}: {}", MANIFEST_FILE, e)))?;
+        Ok((manifest, file))
+    }
+}
+
+/// Reads signed input files and records everything loaded.
+pub struct Inputs {
+    pub dir: InputDir,
+    keyring: Keyring,
+    pub loaded: Vec<LoadedInput>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoadedInput {
+    pub file: String,
+    pub sha256: String,
+    /// Fingerprint of the key which signed the file, none for the embedded manifest.
+    pub signer: Option<String>,
+}
+
+impl Inputs {
+    /// Verifies `manifest` by its detached signature in an input directory, the embedded manifest
+    /// is built into the binary together with the keyring.
+    pub fn new(dir: InputDir, keyring: Keyring, manifest: &InputFile) -> Result<Inputs, Error> {
+        let mut inputs = Inputs {
+            dir,
+            keyring,
+            loaded: vec![],
+        };
+        let signer = match inputs.dir {
+            InputDir::Embedded => None,
+            InputDir::Path(_) => Some(inputs.verify(manifest)?),
+        };
+        inputs.loaded.push(LoadedInput {
+            file: manifest.name.clone(),
+            sha256: manifest.sha256.clone(),
+            signer,
+        });
+        Ok(inputs)
+    }
+
+    /// Reads `name` after verifying its detached signature `name.asc`.
+    pub fn read(&mut self, name: &str) -> Result<(InputFile, String), Error> {
+        let file = self.dir.read(name)?;
+        let signer = self.verify(&file)?;
+        self.loaded.push(LoadedInput {
+            file: file.name.clone(),
+            sha256: file.sha256.clone(),
+            signer: Some(signer.clone()),
+        });
+        Ok((file, signer))
+    }
+
+    fn verify(&self, file: &InputFile) -> Result<String, Error> {
+        let signature = self.dir.read(&format!("{}.asc", file.name))?;
+        self.keyring
+            .verify(&file.content, &signature.content)
+            .map_err(|e| InputError(format!("{}: {}", signature.name, e)).into())
+    }
+}
+
+/// Written next to the generated spec to reproduce it.
+#[derive(Debug, Serialize)]
+pub struct OutputManifest<'a> {
+    pub version: &'a str,
+    pub target: u64,
+    pub input_dir: String,
+    pub lenient: bool,
+    pub duplicates: DuplicatePolicy,
+    /// The calibrated epoch duration lock dates are converted with, if any.
+    pub epoch_duration: Option<u64>,
+    pub inputs: &'a [LoadedInput],
+}
+
+impl fmt::Display for InputDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputDir::Embedded => write!(f, "<embedded>"),
+            InputDir::Path(dir) => write!(f, "{}", dir.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::{short_lock, CodeHashIndex};
+    use crate::signature::TRUSTED_FINGERPRINTS;
+    use chrono::TimeZone;
+    use ckb_types::bytes::Bytes;
+
+    const MANIFEST: &str = r#"
+total = 1_000
+
+[[allocate]]
+file = "allocate.csv"
+
+[[competition]]
+file = "round1.csv"
+network = "testnet"
+
+[[partitions]]
+name = "burn"
+kind = "burn"
+share = "10%"
+locks = ["burn"]
+
+[[partitions]]
+name = "sale"
+kind = "allocate"
+share = "40%"
+sources = ["allocate.csv"]
+locks = ["sighash"]
+
+[[partitions]]
+name = "team"
+kind = "allocate"
+share = "20%"
+sources = ["allocate.csv"]
+locks = ["sighash", "multisig"]
+
+[[partitions]]
+name = "foundation"
+kind = "foundation"
+share = "20%"
+locks = ["multisig"]
+address = "ckb1qyqyz340d4nhgtx2s75mp5wnavrsu7j5fcwqktprrp"
+lock = "2020-07-01"
+
+[[partitions]]
+name = "incentives"
+kind = "incentives"
+share = "10%"
+sources = ["round1.csv"]
+locks = ["sighash"]
+address = "ckb1qyqy6mtud5sgctjwgg6gydd0ea05mr339lnslczzrc"
+"#;
+
+    fn manifest() -> Manifest {
+        toml::from_str(MANIFEST).unwrap()
+    }
+
+    fn cell(ckb: u64, index: CodeHashIndex, category: Option<&str>) -> IssuedCell {
+        IssuedCell {
+            category: category.map(str::to_string),
+            ..IssuedCell::new(ckb * BYTE_SHANNONS, short_lock(index, &Bytes::new()))
+        }
+    }
+
+    fn check(manifest: &Manifest, allocate: Vec<IssuedCell>) -> Vec<PartitionCheck> {
+        let allocate = vec![("allocate.csv".to_string(), allocate)];
+        let foundation_reserve = cell(150, CodeHashIndex::Multisig, None);
+        let testnet_incentives = vec![cell(100, CodeHashIndex::Sighash, None)];
+        let issued = Issued {
+            burn: Capacity::shannons(100 * BYTE_SHANNONS),
+            occupied: Capacity::shannons(50 * BYTE_SHANNONS),
+            allocate: &allocate,
+            foundation_reserve: &foundation_reserve,
+            testnet_incentives: &testnet_incentives,
+        };
+        manifest.check(&issued).unwrap()
+    }
+
+    #[test]
+    fn test_parse_share() {
+        assert_eq!(parse_share("21.5%"), Some(2150));
+        assert_eq!(parse_share("0.01%"), Some(1));
+        assert_eq!(parse_share("100%"), Some(10_000));
+        assert_eq!(parse_share("21.5"), None);
+        assert_eq!(parse_share("21.505%"), None);
+        assert_eq!(parse_share(".5%"), None);
+        assert_eq!(parse_share("-1%"), None);
+    }
+
+    #[test]
+    fn test_embedded_manifest() {
+        let (manifest, _) = InputDir::Embedded.manifest().unwrap();
+        manifest.validate().unwrap();
+    }
+
+    #[test]
+    fn test_embedded_signatures() {
+        let keyring = InputDir::Embedded.read(KEYRING_FILE).unwrap();
+        let keyring = Keyring::from_armor(&keyring.content).unwrap();
+        let signatures: Vec<_> = EMBEDDED
+            .iter()
+            .filter(|(name, _)| name.ends_with(".asc") && *name != KEYRING_FILE)
+            .collect();
+        assert_eq!(signatures.len(), 9);
+        for (name, signature) in signatures {
+            let file = InputDir::Embedded
+                .read(name.trim_end_matches(".asc"))
+                .unwrap();
+            let signer = keyring.verify(&file.content, signature).unwrap();
+            assert_eq!(signer, TRUSTED_FINGERPRINTS[0], "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_outset() {
+        let dt = Utc.ymd(2020, 7, 1).and_hms(0, 0, 0);
+        let outset = manifest().outset().unwrap();
+        assert_eq!(outset, Outset::default());
+        assert_eq!(outset.since_epoch(&dt, 89), 0x2007_0803_8400_0556);
+
+        let config = "[outset]\nanchor = \"2019-11-16T14:00:00+08:00\"\nepoch_duration = 7_200\n";
+        let manifest: Manifest = toml::from_str(&format!("{}{}", MANIFEST, config)).unwrap();
+        let outset = manifest.outset().unwrap();
+        assert_eq!(outset.anchor, Outset::default().anchor);
+        assert_eq!(outset.since_epoch(&dt, 89), 0x2007_0800_0000_0aad);
+    }
+
+    #[test]
+    fn test_validate() {
+        manifest().validate().unwrap();
+
+        let mut manifest = manifest();
+        manifest.partitions[1].share = "39.5%".to_string();
+        assert_eq!(
+            manifest.validate().unwrap_err().to_string(),
+            "partition shares add up to 99.50%"
+        );
+
+        let mut manifest = self::manifest();
+        manifest.partitions[1].sources = vec!["round1.csv".to_string()];
+        assert_eq!(
+            manifest.validate().unwrap_err().to_string(),
+            "source round1.csv of partition sale is not listed as Allocate input"
+        );
+
+        let mut manifest = self::manifest();
+        manifest.partitions[0].sources = vec!["allocate.csv".to_string()];
+        assert_eq!(
+            manifest.validate().unwrap_err().to_string(),
+            "partition burn cannot have sources"
+        );
+
+        let mut manifest = self::manifest();
+        manifest.partitions[4].sources.clear();
+        assert_eq!(
+            manifest.validate().unwrap_err().to_string(),
+            "input round1.csv does not belong to any partition"
+        );
+
+        let mut manifest = self::manifest();
+        manifest.partitions[1].label = Some("Sale\n[genesis]".to_string());
+        assert_eq!(
+            manifest.validate().unwrap_err().to_string(),
+            "label of partition sale cannot contain line breaks"
+        );
+
+        let mut manifest = self::manifest();
+        manifest.partitions[3].lock = None;
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let manifest = manifest();
+        let checks = check(
+            &manifest,
+            vec![
+                cell(400, CodeHashIndex::Sighash, Some("sale")),
+                cell(150, CodeHashIndex::Sighash, Some("team")),
+                cell(50, CodeHashIndex::Multisig, Some("team")),
+            ],
+        );
+        let names: Vec<_> = checks.iter().map(|check| check.name.as_str()).collect();
+        assert_eq!(names, ["burn", "sale", "team", "foundation", "incentives"]);
+        assert!(checks.iter().all(PartitionCheck::is_ok));
+
+        // checked together unless every row has a category
+        let checks = check(
+            &manifest,
+            vec![
+                cell(400, CodeHashIndex::Sighash, Some("sale")),
+                cell(150, CodeHashIndex::Sighash, None),
+                cell(50, CodeHashIndex::Multisig, Some("team")),
+            ],
+        );
+        assert_eq!(checks[1].name, "sale+team");
+        assert_eq!(checks[1].share, 6000);
+        assert!(checks[1].is_ok());
+
+        let checks = check(
+            &manifest,
+            vec![
+                cell(400, CodeHashIndex::Multisig, Some("sale")),
+                cell(100, CodeHashIndex::Sighash, Some("team")),
+            ],
+        );
+        assert!(checks[1].expected == checks[1].actual && !checks[1].is_ok());
+        assert_eq!(checks[1].violations.len(), 1);
+        assert_eq!(checks[2].expected, Capacity::shannons(200 * BYTE_SHANNONS));
+        assert_eq!(checks[2].actual, Capacity::shannons(100 * BYTE_SHANNONS));
+        assert!(!checks[2].is_ok());
+    }
+}
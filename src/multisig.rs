@@ -0,0 +1,132 @@
+use crate::address::{Address, AddressPayload, CodeHashIndex, NetworkType};
+use crate::input::blake160;
+use crate::manifest::InputError;
+use ckb_types::bytes::Bytes;
+use failure::Error;
+use std::collections::HashSet;
+
+/// Signers of a `secp256k1_blake160_multisig_all` lock, any `threshold` of them can unlock it as
+/// long as the first `require_first_n` signers are among them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigConfig {
+    require_first_n: u8,
+    threshold: u8,
+    pubkey_hashes: Vec<Bytes>,
+}
+
+impl MultisigConfig {
+    pub fn new(
+        pubkey_hashes: Vec<Bytes>,
+        threshold: u8,
+        require_first_n: u8,
+    ) -> Result<MultisigConfig, Error> {
+        if pubkey_hashes.is_empty() || pubkey_hashes.len() > usize::from(u8::max_value()) {
+            return Err(InputError(format!(
+                "expect 1 to 255 signers, got {}",
+                pubkey_hashes.len()
+            ))
+            .into());
+        }
+        if threshold == 0 || usize::from(threshold) > pubkey_hashes.len() {
+            return Err(InputError(format!(
+                "threshold must be between 1 and {}, got {}",
+                pubkey_hashes.len(),
+                threshold
+            ))
+            .into());
+        }
+        if require_first_n > threshold {
+            return Err(InputError(format!(
+                "require_first_n must not exceed the threshold {}, got {}",
+                threshold, require_first_n
+            ))
+            .into());
+        }
+        let mut seen = HashSet::new();
+        for pubkey_hash in &pubkey_hashes {
+            if !seen.insert(pubkey_hash) {
+                return Err(InputError(format!(
+                    "duplicate signer: 0x{}",
+                    faster_hex::hex_string(&pubkey_hash[..]).unwrap()
+                ))
+                .into());
+            }
+        }
+
+        Ok(MultisigConfig {
+            require_first_n,
+            threshold,
+            pubkey_hashes,
+        })
+    }
+
+    /// Builds the config from short sighash addresses, which must belong to `network`.
+    pub fn from_addresses<S: AsRef<str>>(
+        addresses: &[S],
+        network: NetworkType,
+        threshold: u8,
+        require_first_n: u8,
+    ) -> Result<MultisigConfig, Error> {
+        let mut pubkey_hashes = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let address = address.as_ref();
+            match Address::from_str_on(address, network)?.payload {
+                AddressPayload::Short {
+                    index: CodeHashIndex::Sighash,
+                    args,
+                } => pubkey_hashes.push(args),
+                _ => {
+                    return Err(InputError(format!(
+                        "signer must be a short sighash address: {}",
+                        address
+                    ))
+                    .into());
+                }
+            }
+        }
+        MultisigConfig::new(pubkey_hashes, threshold, require_first_n)
+    }
+
+    /// The multisig script `S | R | M | N | PubkeyHash...`, where S is reserved as 0.
+    pub fn script(&self) -> Bytes {
+        let mut script = vec![
+            0u8,
+            self.require_first_n,
+            self.threshold,
+            self.pubkey_hashes.len() as u8,
+        ];
+        for pubkey_hash in &self.pubkey_hashes {
+            script.extend_from_slice(pubkey_hash);
+        }
+        Bytes::from(script)
+    }
+
+    /// The multisig script hash, the args of the multisig lock without since.
+    pub fn hash(&self) -> Bytes {
+        blake160(&self.script())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multisig_config() {
+        let signers = [
+            "ckb1qyq05wh6yy6rr8u5w88jzqj0qv5rr0zx2xksf7vqjq",
+            "ckb1qyqyz340d4nhgtx2s75mp5wnavrsu7j5fcwqktprrp",
+            "ckb1qyqy6mtud5sgctjwgg6gydd0ea05mr339lnslczzrc",
+        ];
+        let config = MultisigConfig::from_addresses(&signers, NetworkType::Mainnet, 2, 1).unwrap();
+        let script = config.script();
+        assert_eq!(&script[..4], &[0, 1, 2, 3]);
+        assert_eq!(script.len(), 4 + 20 * 3);
+
+        assert!(MultisigConfig::from_addresses(&signers, NetworkType::Mainnet, 4, 0).is_err());
+        assert!(MultisigConfig::from_addresses(&signers, NetworkType::Mainnet, 2, 3).is_err());
+        assert!(MultisigConfig::from_addresses(&signers, NetworkType::Testnet, 2, 0).is_err());
+        let repeated = [signers[0], signers[1], signers[0]];
+        assert!(MultisigConfig::from_addresses(&repeated, NetworkType::Mainnet, 2, 0).is_err());
+    }
+}
@@ -1,3 +1,9 @@
+use super::error::Error;
+use ckb_jsonrpc_types::response::Output;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+
 #[macro_export]
 macro_rules! jsonrpc {
     (
@@ -15,38 +21,72 @@ macro_rules! jsonrpc {
             pub id_generator: $crate::rpc::id_generator::IdGenerator,
         }
 
-        impl $struct_name {
-            pub fn new(url: &str) -> Self {
-                let url = reqwest::Url::parse(url).expect("ckb url, e.g. \"http://127.0.0.1:8114\"");
-                let id_generator = $crate::rpc::id_generator::IdGenerator::new();
-                $struct_name { url, id_generator, client: &$crate::rpc::HTTP_CLIENT, }
-            }
+        paste::item! {
+            impl $struct_name {
+                pub fn new(url: &str) -> Self {
+                    let url = reqwest::Url::parse(url).expect("ckb url, e.g. \"http://127.0.0.1:8114\"");
+                    let id_generator = $crate::rpc::id_generator::IdGenerator::new();
+                    $struct_name { url, id_generator, client: &$crate::rpc::HTTP_CLIENT, }
+                }
+
+                $(
+                    $(#[$attr])*
+                    #[allow(dead_code)]
+                    pub fn $method(&$selff $(, $arg_name: $arg_ty)*) -> Result<$return_ty, failure::Error> {
+                        let method = String::from(stringify!($method));
+                        let params = serialize_parameters!($($arg_name,)*);
+                        let id = $selff.id_generator.next();
 
-            $(
-                $(#[$attr])*
-                pub fn $method(&$selff $(, $arg_name: $arg_ty)*) -> Result<$return_ty, failure::Error> {
-                    let method = String::from(stringify!($method));
-                    let params = serialize_parameters!($($arg_name,)*);
-                    let id = $selff.id_generator.next();
-
-                    let mut req_json = serde_json::Map::new();
-                    req_json.insert("id".to_owned(), serde_json::json!(id));
-                    req_json.insert("jsonrpc".to_owned(), serde_json::json!("2.0"));
-                    req_json.insert("method".to_owned(), serde_json::json!(method));
-                    req_json.insert("params".to_owned(), params);
-
-                    let mut resp = $selff.client.post($selff.url.clone()).json(&req_json).send()?;
-                    let output = resp.json::<ckb_jsonrpc_types::response::Output>()?;
-                    match output {
-                        ckb_jsonrpc_types::response::Output::Success(success) => {
-                            serde_json::from_value(success.result).map_err(Into::into)
-                        },
-                        ckb_jsonrpc_types::response::Output::Failure(failure) => {
-                            Err($crate::rpc::error::Error{ inner: failure.error }.into())
+                        let mut req_json = serde_json::Map::new();
+                        req_json.insert("id".to_owned(), serde_json::json!(id));
+                        req_json.insert("jsonrpc".to_owned(), serde_json::json!("2.0"));
+                        req_json.insert("method".to_owned(), serde_json::json!(method));
+                        req_json.insert("params".to_owned(), params);
+
+                        let mut resp = $selff.client.post($selff.url.clone()).json(&req_json).send()?;
+                        let output = resp.json::<ckb_jsonrpc_types::response::Output>()?;
+                        match output {
+                            ckb_jsonrpc_types::response::Output::Success(success) => {
+                                serde_json::from_value(success.result).map_err(Into::into)
+                            },
+                            ckb_jsonrpc_types::response::Output::Failure(failure) => {
+                                Err($crate::rpc::error::Error{ inner: failure.error }.into())
+                            }
                         }
                     }
-                }
-            )*
+
+                    /// Sends the calls in one JSON-RPC batch request, the results are in the same
+                    /// order as `params`.
+                    #[allow(dead_code)]
+                    pub fn [<$method _batch>](
+                        &$selff,
+                        params: Vec<($($arg_ty,)*)>,
+                    ) -> Result<Vec<$return_ty>, failure::Error> {
+                        if params.is_empty() {
+                            return Ok(vec![]);
+                        }
+                        let method = String::from(stringify!($method));
+                        let mut ids = std::collections::HashMap::with_capacity(params.len());
+                        let mut req_json = Vec::with_capacity(params.len());
+                        for (i, params) in params.into_iter().enumerate() {
+                            let id = $selff.id_generator.next();
+                            ids.insert(id, i);
+
+                            let mut call = serde_json::Map::new();
+                            call.insert("id".to_owned(), serde_json::json!(id));
+                            call.insert("jsonrpc".to_owned(), serde_json::json!("2.0"));
+                            call.insert("method".to_owned(), serde_json::json!(method));
+                            call.insert("params".to_owned(), serde_json::to_value(params)?);
+                            req_json.push(call);
+                        }
+
+                        let mut resp =
+                            $selff.client.post($selff.url.clone()).json(&req_json).send()?;
+                        let outputs = resp.json::<Vec<serde_json::Value>>()?;
+                        $crate::rpc::macros::batch_results(&method, ids, outputs)
+                    }
+                )*
+            }
         }
     )
 }
@@ -57,3 +97,84 @@ macro_rules! serialize_parameters {
     () => ( serde_json::Value::Null );
     ($($arg_name:ident,)+) => ( serde_json::to_value(($($arg_name,)+))?)
 }
+
+/// Matches the responses of a batch request to its calls, `ids` maps the id of each call to its
+/// position in the batch.
+pub(in crate::rpc) fn batch_results<T: DeserializeOwned>(
+    method: &str,
+    mut ids: HashMap<u64, usize>,
+    outputs: Vec<Value>,
+) -> Result<Vec<T>, failure::Error> {
+    let mut results: Vec<Option<T>> = (0..ids.len()).map(|_| None).collect();
+    for output in outputs {
+        let i = output["id"]
+            .as_u64()
+            .and_then(|id| ids.remove(&id))
+            .ok_or_else(|| {
+                failure::err_msg(format!(
+                    "{}: unexpected response id {}",
+                    method, output["id"]
+                ))
+            })?;
+        match serde_json::from_value(output)? {
+            Output::Success(success) => {
+                results[i] = Some(serde_json::from_value(success.result)?);
+            }
+            Output::Failure(failure) => {
+                return Err(Error {
+                    inner: failure.error,
+                }
+                .into());
+            }
+        }
+    }
+    results
+        .into_iter()
+        .map(|result| {
+            result.ok_or_else(|| failure::err_msg(format!("{}: missing response", method)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ids() -> HashMap<u64, usize> {
+        vec![(7, 0), (8, 1), (9, 2)].into_iter().collect()
+    }
+
+    fn success(id: u64, result: &str) -> Value {
+        json!({"jsonrpc": "2.0", "id": id, "result": result})
+    }
+
+    #[test]
+    fn test_batch_results() {
+        // the responses may come back in any order
+        let outputs = vec![success(9, "c"), success(7, "a"), success(8, "b")];
+        let results: Vec<String> = batch_results("test", ids(), outputs).unwrap();
+        assert_eq!(results, vec!["a", "b", "c"]);
+
+        let outputs = vec![success(9, "c"), success(7, "a")];
+        let err = batch_results::<String>("test", ids(), outputs).unwrap_err();
+        assert_eq!(err.to_string(), "test: missing response");
+
+        let outputs = vec![success(7, "a"), success(7, "a"), success(8, "b")];
+        let err = batch_results::<String>("test", ids(), outputs).unwrap_err();
+        assert_eq!(err.to_string(), "test: unexpected response id 7");
+
+        let outputs = vec![success(10, "d")];
+        let err = batch_results::<String>("test", ids(), outputs).unwrap_err();
+        assert_eq!(err.to_string(), "test: unexpected response id 10");
+
+        let failure = json!({
+            "jsonrpc": "2.0",
+            "id": 8,
+            "error": {"code": -32601, "message": "Method not found"},
+        });
+        let outputs = vec![success(7, "a"), failure, success(9, "c")];
+        let err = batch_results::<String>("test", ids(), outputs).unwrap_err();
+        assert!(err.downcast_ref::<Error>().is_some());
+    }
+}
@@ -0,0 +1,110 @@
+use failure::{Error, Fail};
+use pgp::composed::{Deserializable, SignedPublicKey, StandaloneSignature};
+use pgp::types::KeyTrait;
+use std::fmt;
+use std::io::Cursor;
+
+/// Fingerprints of the keys trusted to sign the input files, see `src/input/README.md`.
+pub const TRUSTED_FINGERPRINTS: &[&str] = &["0D871C398C182304C46C453630C4B91C7A85D234"];
+
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Fail)]
+pub struct SignatureError(String);
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The public keys whose fingerprints are pinned in `TRUSTED_FINGERPRINTS`.
+pub struct Keyring {
+    keys: Vec<SignedPublicKey>,
+}
+
+impl Keyring {
+    /// Loads the trusted keys from an armored keyring, ignoring keys that are not pinned.
+    pub fn from_armor(input: &[u8]) -> Result<Keyring, Error> {
+        Keyring::from_armor_pinned(input, TRUSTED_FINGERPRINTS)
+    }
+
+    fn from_armor_pinned(input: &[u8], fingerprints: &[&str]) -> Result<Keyring, Error> {
+        let (keys, _) = SignedPublicKey::from_armor_many(Cursor::new(input))?;
+        let mut trusted = vec![];
+        for key in keys {
+            let key = key?;
+            if fingerprints.contains(&fingerprint(&key).as_str()) {
+                key.verify()?;
+                trusted.push(key);
+            }
+        }
+
+        if trusted.is_empty() {
+            return Err(SignatureError(format!(
+                "keyring contains none of the trusted keys: {}",
+                fingerprints.join(", ")
+            ))
+            .into());
+        }
+        Ok(Keyring { keys: trusted })
+    }
+
+    /// Verifies a detached armored signature, returns the fingerprint of the signing key.
+    pub fn verify(&self, content: &[u8], signature: &[u8]) -> Result<String, Error> {
+        let (signature, _) = StandaloneSignature::from_armor_single(Cursor::new(signature))?;
+        for key in &self.keys {
+            if signature.verify(key, content).is_ok()
+                || key
+                    .public_subkeys
+                    .iter()
+                    .any(|subkey| signature.verify(subkey, content).is_ok())
+            {
+                return Ok(fingerprint(key));
+            }
+        }
+        Err(SignatureError("not signed by any trusted key".to_string()).into())
+    }
+}
+
+fn fingerprint(key: &SignedPublicKey) -> String {
+    faster_hex::hex_string(&key.fingerprint())
+        .unwrap()
+        .to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRUSTED: &str = "5BAD9B832508B13ABC2F644D329500049BDD5CA0";
+    const CONTENT: &[u8] = b"address,capacity\n";
+
+    fn keyring() -> Keyring {
+        let mut armor = include_bytes!("signature/trusted.asc").to_vec();
+        armor.extend_from_slice(include_bytes!("signature/untrusted.asc"));
+        Keyring::from_armor_pinned(&armor, &[TRUSTED]).unwrap()
+    }
+
+    #[test]
+    fn test_verify() {
+        let keyring = keyring();
+        assert_eq!(keyring.keys.len(), 1);
+
+        let signature = include_bytes!("signature/primary.csv.asc");
+        assert_eq!(keyring.verify(CONTENT, signature).unwrap(), TRUSTED);
+        assert!(keyring.verify(b"address,capacity\r\n", signature).is_err());
+
+        // signed by the signing subkey of the trusted key
+        let signature = include_bytes!("signature/subkey.csv.asc");
+        assert_eq!(keyring.verify(CONTENT, signature).unwrap(), TRUSTED);
+
+        let signature = include_bytes!("signature/untrusted.csv.asc");
+        assert!(keyring.verify(CONTENT, signature).is_err());
+    }
+
+    #[test]
+    fn test_untrusted_keyring() {
+        let untrusted = include_bytes!("signature/untrusted.asc");
+        assert!(Keyring::from_armor_pinned(untrusted, &[TRUSTED]).is_err());
+        assert!(Keyring::from_armor(include_bytes!("signature/trusted.asc")).is_err());
+    }
+}
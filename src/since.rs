@@ -0,0 +1,233 @@
+use crate::manifest::InputError;
+use chrono::{DateTime, Utc};
+use ckb_types::core::EpochNumberWithFraction;
+use failure::Error;
+use std::fmt;
+
+const RELATIVE_FLAG: u64 = 0x8000_0000_0000_0000;
+const METRIC_MASK: u64 = 0x6000_0000_0000_0000;
+const REMAIN_FLAGS_MASK: u64 = 0x1f00_0000_0000_0000;
+const VALUE_MASK: u64 = 0x00ff_ffff_ffff_ffff;
+const METRIC_BLOCK_NUMBER: u64 = 0x0000_0000_0000_0000;
+const METRIC_EPOCH: u64 = 0x2000_0000_0000_0000;
+const METRIC_TIMESTAMP: u64 = 0x4000_0000_0000_0000;
+const RELATIVE_PREFIX: &str = "relative:";
+const EPOCH_PREFIX: &str = "epoch:";
+const TIMESTAMP_PREFIX: &str = "timestamp:";
+const BLOCK_PREFIX: &str = "block:";
+const MAX_EPOCH_NUMBER: u64 = 0xff_ffff;
+const MAX_EPOCH_LENGTH: u64 = 0xffff;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinceMetric {
+    BlockNumber(u64),
+    Epoch(EpochNumberWithFraction),
+    /// Median time of the past 37 blocks, in seconds.
+    Timestamp(u64),
+}
+
+/// A since value decoded according to RFC 0017.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Since {
+    pub relative: bool,
+    pub metric: SinceMetric,
+}
+
+impl Since {
+    pub fn from_raw(raw: u64) -> Result<Since, Error> {
+        if raw & REMAIN_FLAGS_MASK != 0 {
+            return Err(InputError(format!("invalid since flags: 0x{:016x}", raw)).into());
+        }
+        let value = raw & VALUE_MASK;
+        let metric = match raw & METRIC_MASK {
+            METRIC_BLOCK_NUMBER => SinceMetric::BlockNumber(value),
+            METRIC_EPOCH => SinceMetric::Epoch(EpochNumberWithFraction::from_full_value(value)),
+            METRIC_TIMESTAMP => SinceMetric::Timestamp(value),
+            _ => return Err(InputError(format!("invalid since metric: 0x{:016x}", raw)).into()),
+        };
+        Ok(Since {
+            relative: raw & RELATIVE_FLAG != 0,
+            metric,
+        })
+    }
+
+    pub fn to_raw(&self) -> u64 {
+        let (metric, value) = match self.metric {
+            SinceMetric::BlockNumber(number) => (METRIC_BLOCK_NUMBER, number),
+            SinceMetric::Epoch(epoch) => (METRIC_EPOCH, epoch.full_value()),
+            SinceMetric::Timestamp(timestamp) => (METRIC_TIMESTAMP, timestamp),
+        };
+        let relative = if self.relative { RELATIVE_FLAG } else { 0 };
+        relative | metric | value
+    }
+
+    /// Parses a lock in the form `epoch:<number>[+<index>/<length>]`, `timestamp:<seconds>` or
+    /// `block:<number>`, which is relative to when the cell is committed if prefixed with
+    /// `relative:`. Returns `None` if the lock has none of these prefixes. Absolute timestamps
+    /// must be after `genesis`.
+    pub fn parse_lock(input: &str, genesis: &DateTime<Utc>) -> Result<Option<Since>, Error> {
+        let invalid = || InputError(format!("invalid lock: {}", input));
+        let (relative, lock) = if input.starts_with(RELATIVE_PREFIX) {
+            (true, &input[RELATIVE_PREFIX.len()..])
+        } else {
+            (false, input)
+        };
+        let metric = if lock.starts_with(EPOCH_PREFIX) {
+            let value = &lock[EPOCH_PREFIX.len()..];
+            let (number, fraction) = match value.find('+') {
+                Some(i) => (&value[..i], Some(&value[i + 1..])),
+                None => (value, None),
+            };
+            let number: u64 = number.parse().map_err(|_| invalid())?;
+            let (index, length) = match fraction {
+                Some(fraction) => {
+                    let i = fraction.find('/').ok_or_else(invalid)?;
+                    let index: u64 = fraction[..i].parse().map_err(|_| invalid())?;
+                    let length: u64 = fraction[i + 1..].parse().map_err(|_| invalid())?;
+                    (index, length)
+                }
+                None => (0, 1),
+            };
+            if number > MAX_EPOCH_NUMBER
+                || length == 0
+                || length > MAX_EPOCH_LENGTH
+                || index >= length
+            {
+                return Err(InputError(format!("epoch out of range: {}", input)).into());
+            }
+            SinceMetric::Epoch(EpochNumberWithFraction::new(number, index, length))
+        } else if lock.starts_with(TIMESTAMP_PREFIX) {
+            let timestamp: u64 = lock[TIMESTAMP_PREFIX.len()..]
+                .parse()
+                .map_err(|_| invalid())?;
+            if timestamp > VALUE_MASK {
+                return Err(InputError(format!("timestamp out of range: {}", input)).into());
+            }
+            if !relative && timestamp as i64 <= genesis.timestamp() {
+                return Err(InputError(format!(
+                    "timestamp must be in seconds after the genesis {}: {}",
+                    genesis, input
+                ))
+                .into());
+            }
+            SinceMetric::Timestamp(timestamp)
+        } else if lock.starts_with(BLOCK_PREFIX) {
+            let number: u64 = lock[BLOCK_PREFIX.len()..].parse().map_err(|_| invalid())?;
+            if number > VALUE_MASK {
+                return Err(InputError(format!("block number out of range: {}", input)).into());
+            }
+            SinceMetric::BlockNumber(number)
+        } else if relative {
+            return Err(InputError(format!(
+                "expect epoch:, timestamp: or block: after relative: {}",
+                input
+            ))
+            .into());
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(Since { relative, metric }))
+    }
+
+    /// Parses the args of an issued cell, whose last 8 bytes are the since in little endian, or a
+    /// raw since value in hex with the 0x prefix or in decimal.
+    pub fn parse_raw(input: &str) -> Result<u64, Error> {
+        let invalid = || InputError(format!("invalid since: {}", input));
+        if input.starts_with("0x") && input.len() > 18 {
+            let hex = &input.as_bytes()[2..];
+            if hex.len() % 2 != 0 {
+                return Err(invalid().into());
+            }
+            let mut args = vec![0; hex.len() / 2];
+            faster_hex::hex_decode(hex, &mut args).map_err(|_| invalid())?;
+            let mut since = [0u8; 8];
+            since.copy_from_slice(&args[args.len() - 8..]);
+            Ok(u64::from_le_bytes(since))
+        } else if input.starts_with("0x") {
+            u64::from_str_radix(&input[2..], 16).map_err(|_| invalid().into())
+        } else {
+            input.parse().map_err(|_| invalid().into())
+        }
+    }
+}
+
+impl fmt::Display for Since {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.relative {
+            "relative"
+        } else {
+            "absolute"
+        };
+        match self.metric {
+            SinceMetric::BlockNumber(number) => write!(f, "{} block number {}", kind, number),
+            SinceMetric::Epoch(epoch) => write!(
+                f,
+                "{} epoch {} + {}/{}",
+                kind,
+                epoch.number(),
+                epoch.index(),
+                epoch.length()
+            ),
+            SinceMetric::Timestamp(timestamp) => write!(f, "{} timestamp {}", kind, timestamp),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::TimeZone;
+
+    #[test]
+    fn test_since() {
+        let args = "0x0000000000000000000000000000000000000000330000b400080720";
+        assert_eq!(Since::parse_raw(args).unwrap(), 0x2007_0800_b400_0033);
+        assert_eq!(
+            Since::parse_raw("0x20070800b4000033").unwrap(),
+            0x2007_0800_b400_0033
+        );
+
+        let since = Since::from_raw(0x2007_0800_b400_0033).unwrap();
+        assert!(!since.relative);
+        assert_eq!(
+            since.metric,
+            SinceMetric::Epoch(EpochNumberWithFraction::new(0x33, 0xb4, 1800))
+        );
+        assert!(Since::from_raw(0x0100_0000_0000_0000).is_err());
+    }
+
+    #[test]
+    fn test_parse_lock() {
+        let genesis = Utc.timestamp(1_573_852_190, 0);
+        let parse =
+            |input| Since::parse_lock(input, &genesis).map(|since| since.map(|s| s.to_raw()));
+        assert_eq!(
+            parse("epoch:51+180/1800").unwrap(),
+            Some(0x2007_0800_b400_0033)
+        );
+        assert_eq!(parse("epoch:51").unwrap(), Some(0x2000_0100_0000_0033));
+        assert_eq!(
+            parse("timestamp:1593561600").unwrap(),
+            Some(0x4000_0000_5efb_d200)
+        );
+        assert_eq!(parse("block:1000").unwrap(), Some(1000));
+        assert_eq!(parse("2020-07-01").unwrap(), None);
+        assert!(parse("epoch:51+1800/1800").is_err());
+        assert!(parse("timestamp:1573852190").is_err());
+        assert!(parse("block:0x10").is_err());
+
+        assert_eq!(
+            parse("relative:epoch:6").unwrap(),
+            Some(0xa000_0100_0000_0006)
+        );
+        assert_eq!(
+            parse("relative:timestamp:86400").unwrap(),
+            Some(0xc000_0000_0001_5180)
+        );
+        assert_eq!(
+            parse("relative:block:1000").unwrap(),
+            Some(0x8000_0000_0000_03e8)
+        );
+        assert!(parse("relative:2020-07-01").is_err());
+    }
+}
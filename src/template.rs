@@ -1,3 +1,5 @@
+use crate::address::hash_type_name;
+use ckb_types::{core::ScriptHashType, packed::Script, prelude::*};
 use serde_derive::Serialize;
 
 #[derive(Debug, Serialize)]
@@ -14,6 +16,63 @@ pub struct Spec {
 #[derive(Debug, Serialize)]
 pub struct IssuedCell {
     pub capacity: u64,
+    /// The lock script, rendered in the spec as the following three fields.
+    #[serde(skip)]
+    pub lock: Script,
     pub code_hash: String,
+    /// "data" or "type".
+    pub hash_type: String,
     pub args: String,
+    /// Address the cell is issued to as given in the input, the signers of a multisig lock are
+    /// separated by ";".
+    pub address: Option<String>,
+    /// The lock as given in the input, such as "2020-07-01T09:00:00 Asia/Shanghai".
+    pub lock_input: Option<String>,
+    /// Unlock time in UTC as RFC 3339, if the lock is given as a time or a timestamp since.
+    pub unlock_time: Option<String>,
+    /// Line of the row this cell is generated from.
+    pub line: Option<u64>,
+    /// Partition of the cell given in the category column.
+    pub category: Option<String>,
+    /// Label of the partition, rendered as a comment before the first cell of the partition.
+    pub label: Option<String>,
+}
+
+impl IssuedCell {
+    /// A cell issued to `lock`, without the details of the row it comes from.
+    pub fn new(capacity: u64, lock: Script) -> IssuedCell {
+        let hash_type = match lock.hash_type().as_slice()[0] {
+            0 => ScriptHashType::Data,
+            _ => ScriptHashType::Type,
+        };
+        IssuedCell {
+            capacity,
+            code_hash: hex(lock.code_hash().as_slice()),
+            hash_type: hash_type_name(hash_type).to_string(),
+            args: hex(&lock.args().raw_data()),
+            lock,
+            address: None,
+            lock_input: None,
+            unlock_time: None,
+            line: None,
+            category: None,
+            label: None,
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    format!("0x{}", faster_hex::hex_string(bytes).unwrap())
+}
+
+/// Keeps the label only on the first of the consecutive cells sharing it.
+pub fn dedup_labels(cells: &mut [IssuedCell]) {
+    let mut previous = None;
+    for cell in cells {
+        if cell.label.is_some() && cell.label == previous {
+            cell.label = None;
+        } else {
+            previous = cell.label.clone();
+        }
+    }
 }
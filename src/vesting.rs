@@ -0,0 +1,137 @@
+use crate::date::parse_date;
+use crate::manifest::{parse_share, InputError};
+use chrono::{naive::NaiveDate, DateTime, Datelike, Utc};
+use ckb_types::core::Capacity;
+use failure::Error;
+use serde_derive::Deserialize;
+
+const FULL_SHARE: u128 = 10_000;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Period {
+    fn months(self) -> u32 {
+        match self {
+            Period::Monthly => 1,
+            Period::Quarterly => 3,
+            Period::Yearly => 12,
+        }
+    }
+}
+
+/// A vesting plan referenced as `schedule:<name>` in the lock column, such as 25% at the cliff,
+/// then the rest in equal parts every month over 36 months.
+#[derive(Debug, Deserialize)]
+pub struct Schedule {
+    /// Unlock date of the first tranche, in the format YYYY-MM-DD.
+    pub cliff: String,
+    /// Percentage unlocked at the cliff, such as "25%".
+    pub cliff_share: String,
+    #[serde(default = "default_period")]
+    pub period: Period,
+    /// Number of tranches after the cliff.
+    #[serde(default)]
+    pub periods: u32,
+}
+
+fn default_period() -> Period {
+    Period::Monthly
+}
+
+impl Schedule {
+    /// Splits `capacity` into tranches with their unlock dates. The rounding remainder goes to the
+    /// last tranche, so the tranches always add up to `capacity`.
+    pub fn expand(&self, capacity: Capacity) -> Result<Vec<(DateTime<Utc>, Capacity)>, Error> {
+        let cliff = parse_date(&self.cliff)?;
+        let share = parse_share(&self.cliff_share)
+            .filter(|&share| u128::from(share) <= FULL_SHARE)
+            .ok_or_else(|| InputError(format!("invalid cliff share: {}", self.cliff_share)))?;
+        if self.periods == 0 && u128::from(share) != FULL_SHARE {
+            return Err(InputError(
+                "schedule without periods must unlock 100% at the cliff".to_string(),
+            )
+            .into());
+        }
+
+        let total = capacity.as_u64();
+        let at_cliff = (u128::from(total) * u128::from(share) / FULL_SHARE) as u64;
+        let mut tranches = vec![(cliff, Capacity::shannons(at_cliff))];
+        if self.periods > 0 {
+            let rest = total - at_cliff;
+            let each = rest / u64::from(self.periods);
+            for i in 1..=self.periods {
+                let date = add_months(cliff.naive_utc().date(), i * self.period.months())
+                    .ok_or_else(|| InputError(format!("schedule out of range: {}", self.cliff)))?;
+                let amount = if i == self.periods {
+                    rest - each * u64::from(self.periods - 1)
+                } else {
+                    each
+                };
+                tranches.push((
+                    DateTime::from_utc(date.and_hms(0, 0, 0), Utc),
+                    Capacity::shannons(amount),
+                ));
+            }
+        }
+
+        let sum = tranches
+            .iter()
+            .map(|(_, amount)| *amount)
+            .try_fold(Capacity::zero(), Capacity::safe_add)?;
+        if sum != capacity {
+            return Err(InputError(format!(
+                "tranches add up to {} shannons, expect {}",
+                sum.as_u64(),
+                total
+            ))
+            .into());
+        }
+        Ok(tranches)
+    }
+}
+
+/// Adds calendar months, clamping the day to the end of shorter months.
+fn add_months(date: NaiveDate, months: u32) -> Option<NaiveDate> {
+    let month0 = date.month0() + months;
+    let year = date.year() + (month0 / 12) as i32;
+    let month = month0 % 12 + 1;
+    (1..=date.day())
+        .rev()
+        .filter_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::TimeZone;
+
+    #[test]
+    fn test_expand() {
+        let schedule = Schedule {
+            cliff: "2020-01-31".to_string(),
+            cliff_share: "25%".to_string(),
+            period: Period::Monthly,
+            periods: 36,
+        };
+        let tranches = schedule.expand(Capacity::shannons(1_000_000_001)).unwrap();
+        assert_eq!(tranches.len(), 37);
+        assert_eq!(
+            tranches[0],
+            (
+                Utc.ymd(2020, 1, 31).and_hms(0, 0, 0),
+                Capacity::shannons(250_000_000)
+            )
+        );
+        assert_eq!(tranches[1].0, Utc.ymd(2020, 2, 29).and_hms(0, 0, 0));
+        assert_eq!(tranches[36].0, Utc.ymd(2023, 1, 31).and_hms(0, 0, 0));
+        assert_eq!(tranches[1].1, Capacity::shannons(20_833_333));
+        assert_eq!(tranches[36].1, Capacity::shannons(20_833_346));
+    }
+}